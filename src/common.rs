@@ -2,6 +2,6 @@ pub use std::io::{self, Write};
 
 macro_rules! warn {
     ($fmt:expr $(, $arg:expr )* $(,)*) => {
-        let _ = write!(io::stderr(), "WARN: {}", format!($fmt, $( $arg, )*));
+        let _ = writeln!(io::stderr(), "WARN: {}", format!($fmt, $( $arg, )*));
     };
 }
@@ -0,0 +1,83 @@
+//! Module for the configurable parts of the line mode document format.
+
+use std_unicode::str::UnicodeStr;
+use error::{KorError, FileKind};
+use document::{AUTO_PREFIX, MANUAL_PREFIX, COMMENT_PREFIX, MANUAL_LINES};
+
+/// The name of the project configuration file read from the current
+/// directory, when no other file is given.
+pub const CONFIG_FILE: &str = "kor.conf";
+
+/// The markers used in line mode documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Markers {
+    /// The prefix of automatically translated lines.
+    pub auto_prefix: String,
+    /// The prefix of lines for manual translations.
+    pub manual_prefix: String,
+    /// The prefix of comment lines.
+    pub comment_prefix: String,
+    /// The number of blank manual translation lines placed under each line.
+    pub manual_lines: usize,
+}
+
+impl Default for Markers {
+    fn default() -> Markers {
+        Markers {
+            auto_prefix: AUTO_PREFIX.to_string(),
+            manual_prefix: MANUAL_PREFIX.to_string(),
+            comment_prefix: COMMENT_PREFIX.to_string(),
+            manual_lines: MANUAL_LINES,
+        }
+    }
+}
+
+impl Markers {
+    /// Reads the settings of a configuration file, replacing the current ones.
+    /// Each line has the format '<setting> = <value>'.
+    /// Ex: 'auto-prefix = =>'.
+    pub fn read_config(&mut self, text: &str, path: &str) -> Result<(), KorError> {
+        for (i, line) in text.lines().enumerate() {
+            if line.trim_left().starts_with("#") || line.is_whitespace() {
+                continue;
+            }
+            let parse_error = |message: String| KorError::Parse {
+                kind: FileKind::Config, path: path.to_string(), line: i+1, message,
+            };
+            let (key, value) = match line.find('=') {
+                Some(pos) => ((&line[..pos]).trim(), (&line[pos+1..]).trim()),
+                None => return Err(parse_error(format!("Expected '<setting> = <value>': {:?}", line))),
+            };
+            match key {
+                "auto-prefix" => self.auto_prefix = value.to_string(),
+                "manual-prefix" => self.manual_prefix = value.to_string(),
+                "comment-prefix" => self.comment_prefix = value.to_string(),
+                "manual-lines" => {
+                    self.manual_lines = value.parse().map_err(|_| {
+                        parse_error(format!("Invalid number of manual lines: {:?}", value))
+                    })?;
+                }
+                _ => return Err(parse_error(format!("Unknown setting: {:?}", key))),
+            }
+        }
+        self.validate().map_err(|message| KorError::Parse {
+            kind: FileKind::Config, path: path.to_string(), line: 0, message,
+        })
+    }
+
+    /// Checks that the markers can be told apart in a document.
+    pub fn validate(&self) -> Result<(), String> {
+        let prefixes = [&self.auto_prefix, &self.manual_prefix, &self.comment_prefix];
+        for (i, a) in prefixes.iter().enumerate() {
+            if a.trim().is_empty() {
+                return Err("The line prefixes can't be empty".to_string());
+            }
+            for b in &prefixes[i+1..] {
+                if a.starts_with(b.as_str()) || b.starts_with(a.as_str()) {
+                    return Err(format!("The line prefixes {:?} and {:?} can't be told apart", a, b));
+                }
+            }
+        }
+        Ok(())
+    }
+}
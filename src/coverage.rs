@@ -0,0 +1,180 @@
+//! Module for measuring how much of a text the dictionary covers.
+
+use std::collections::HashMap;
+use hangeul2::is_hangeul;
+use dict::Dict;
+use hanja::is_hanja;
+use translate::{TranslationPart, translate_iter_excluding};
+use unknown::strip_particle;
+use exclusion::ExclusionOverlay;
+use config::Markers;
+
+/// The name used for definitions without a known word list.
+pub const UNKNOWN_SOURCE: &str = "<unknown>";
+
+/// How much of a text is covered by the dictionary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// The number of hangeul characters.
+    pub chars: usize,
+    /// The hangeul characters in translated words.
+    pub covered_chars: usize,
+    /// The number of Korean words.
+    pub words: usize,
+    /// The Korean words whose stem (the word without a particle) is
+    /// translated.
+    pub covered_words: usize,
+    /// The Korean words with a translated part, but an untranslated stem.
+    pub partly_covered_words: usize,
+}
+
+impl Coverage {
+    /// Adds the counts of another coverage to this one.
+    pub fn add(&mut self, other: &Coverage) {
+        self.chars += other.chars;
+        self.covered_chars += other.covered_chars;
+        self.words += other.words;
+        self.covered_words += other.covered_words;
+        self.partly_covered_words += other.partly_covered_words;
+    }
+
+    /// Returns the percentage of hangeul characters that are covered.
+    pub fn char_percentage(&self) -> f64 {
+        percentage(self.covered_chars, self.chars)
+    }
+
+    /// Returns the percentage of words that are covered.
+    pub fn word_percentage(&self) -> f64 {
+        percentage(self.covered_words, self.words)
+    }
+}
+
+/// Returns the part as a percentage of the total.
+pub fn percentage(part: usize, total: usize) -> f64 {
+    if total == 0 { 0.0 } else { 100.0 * part as f64 / total as f64 }
+}
+
+/// Returns whether the character is written in hangeul or hanja.
+fn is_korean(ch: char) -> bool {
+    is_hangeul(ch) || is_hanja(ch)
+}
+
+/// Returns where the stem of a word is: its Korean text, without the
+/// punctuation around it or a particle at its end.
+fn stem_range(word: &str, dict: &Dict) -> (usize, usize) {
+    let start = word.find(is_korean).unwrap_or(0);
+    let end = word.char_indices().filter(|&(_, ch)| is_korean(ch)).last()
+        .map(|(i, ch)| i + ch.len_utf8())
+        .unwrap_or(word.len());
+    let stem = strip_particle(&word[start..end], dict);
+    (start, start + stem.len())
+}
+
+/// Finds how much of a line the dictionary covers, leaving out the words
+/// excluded by the overlay, and counts the translated words by the word list
+/// of their definition.
+pub fn line_coverage(line: &str, dict: &Dict, exclusions: &ExclusionOverlay, 
+        sources: &mut HashMap<String, usize>) -> Coverage {
+    let mut coverage = Coverage::default();
+    let mut translated_ranges = Vec::new();
+    translate_iter_excluding(line, dict, exclusions, |part| {
+        if let TranslationPart::Translated(src, def) = part {
+            let start = src.as_ptr() as usize - line.as_ptr() as usize;
+            translated_ranges.push((start, start + src.len()));
+            coverage.covered_chars += src.chars().filter(|&ch| is_hangeul(ch)).count();
+            let source = dict.source_of(def).unwrap_or(UNKNOWN_SOURCE);
+            *sources.entry(source.to_string()).or_insert(0) += 1;
+        }
+    });
+    coverage.chars = line.chars().filter(|&ch| is_hangeul(ch)).count();
+
+    let mut pos = 0;
+    for word in line.split_whitespace() {
+        let start = pos + line[pos..].find(word).unwrap();
+        let end = start + word.len();
+        pos = end;
+        if ! word.chars().any(is_korean) {
+            continue;
+        }
+        coverage.words += 1;
+        let (stem_start, stem_end) = stem_range(word, dict);
+        let is_translated = |i: usize| translated_ranges.iter().any(|&(s, e)| s <= i && i < e);
+        let stem_covered = word[stem_start..stem_end].char_indices()
+            .filter(|&(_, ch)| is_korean(ch))
+            .all(|(i, _)| is_translated(start + stem_start + i));
+        if stem_covered {
+            coverage.covered_words += 1;
+        } else if translated_ranges.iter().any(|&(s, e)| s < end && start < e) {
+            coverage.partly_covered_words += 1;
+        }
+    }
+    coverage
+}
+
+/// The dictionary coverage of a document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentCoverage {
+    /// The coverage of each line with Korean text, by line number.
+    pub lines: Vec<(usize, Coverage)>,
+    pub total: Coverage,
+    /// The number of translated words from each word list, the most used
+    /// first.
+    pub sources: Vec<(String, usize)>,
+}
+
+/// Finds how much of each line of the text the dictionary covers.
+/// Exclusion directives in the text apply to the lines after them.
+pub fn document_coverage(text: &str, dict: &Dict, markers: &Markers) -> DocumentCoverage {
+    let mut coverage = DocumentCoverage::default();
+    let mut sources = HashMap::new();
+    let mut exclusions = ExclusionOverlay::new();
+    for (i, line) in text.lines().enumerate() {
+        if exclusions.apply_directive(line, &markers.comment_prefix) {
+            continue;
+        }
+        let covered = line_coverage(line, dict, &exclusions, &mut sources);
+        if covered.words == 0 {
+            continue;
+        }
+        coverage.total.add(&covered);
+        coverage.lines.push((i + 1, covered));
+    }
+    coverage.sources = sources.into_iter().collect();
+    coverage.sources.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    coverage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn school_dict() -> Dict<'static> {
+        let mut dict = Dict::new();
+        dict.add_word_list("학교\n  school\n");
+        dict
+    }
+
+    #[test]
+    fn words_are_covered_by_their_stem() {
+        let dict = school_dict();
+        let coverage = line_coverage("\"학교에\" 학교생활 갔다.", &dict, &ExclusionOverlay::new(), 
+            &mut HashMap::new());
+        assert_eq!(coverage.words, 3);
+        assert_eq!(coverage.covered_words, 1);
+        assert_eq!(coverage.partly_covered_words, 1);
+        assert_eq!(coverage.chars, 9);
+        assert_eq!(coverage.covered_chars, 4);
+    }
+
+    #[test]
+    fn directives_apply_to_the_lines_after_them() {
+        let dict = school_dict();
+        let coverage = document_coverage("학교에\n-# exclude: 학교\n학교에", &dict, 
+            &Markers::default());
+        assert_eq!(coverage.lines.len(), 2);
+        assert_eq!(coverage.lines[0].0, 1);
+        assert_eq!(coverage.lines[1].0, 3);
+        assert_eq!(coverage.total.words, 2);
+        assert_eq!(coverage.total.covered_words, 1);
+    }
+}
@@ -1,13 +1,75 @@
 //! Module for working with dictionaries.
 
 use std::rc::Rc;
+use std::fmt;
+use std::collections::HashMap;
+use regex::{self, Regex};
 use trie::Trie;
-use wordlist::Def;
+use wordlist::{Def, read_definitions};
+use hanja;
+use error::KorError;
+use exclusion::{ExclusionRule, ExclusionOverlay, Pattern, read_exclusions};
+use normalize::{nfc, nfc_in_place};
 use hangeul2::{Block, Initial, is_hangeul};
+use common::*;
+
+/// How a dictionary key was made from its definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyOrigin {
+    /// The headword itself.
+    Headword,
+    /// One of the aliases of the definition.
+    Alias(String),
+    /// The given headword or alias without its '하다' ending.
+    HadaStem(String),
+    /// A form of the given headword or alias, made by the named rule.
+    Conjugation { word: String, rule: &'static str },
+    /// A hanja spelling of the definition.
+    Hanja(String),
+    /// A key inserted directly.
+    Inserted,
+}
+
+impl fmt::Display for KeyOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::KeyOrigin::*;
+        match *self {
+            Headword => write!(f, "headword"),
+            Alias(ref alias) => write!(f, "alias {:?}", alias),
+            HadaStem(ref word) => write!(f, "{:?} without '하다'", word),
+            Conjugation { ref word, rule } => write!(f, "form of {:?}: {}", word, rule),
+            Hanja(ref text) => write!(f, "hanja {:?}", text),
+            Inserted => write!(f, "inserted"),
+        }
+    }
+}
+
+/// A definition of the dictionary, with the keys that were made for it.
+#[derive(Debug)]
+struct Entry<'defsrc> {
+    def: Rc<Def<'defsrc>>,
+    keys: Vec<String>,
+}
 
 /// The dictionary type used for translations.
 pub struct Dict<'defsrc> {
     inner: Trie<Rc<Def<'defsrc>>>,
+    /// The definitions that were added, or None once they are removed.
+    entries: Vec<Option<Entry<'defsrc>>>,
+    /// The entries of each headword and alias.
+    headwords: HashMap<String, Vec<usize>>,
+    /// Definitions by their full hanja (without markers).
+    hanja_words: HashMap<String, Vec<Rc<Def<'defsrc>>>>,
+    /// Definitions by each hanja character they contain.
+    hanja_chars: HashMap<char, Vec<Rc<Def<'defsrc>>>>,
+    /// Hanja read as each hangeul syllable, with how often they are used.
+    readings: HashMap<char, Vec<(char, usize)>>,
+    /// Exclusion rules that only apply in some contexts.
+    exclusions: ExclusionOverlay,
+    /// How each key was made.
+    origins: HashMap<String, KeyOrigin>,
+    /// The names of the word lists definitions were read from.
+    source_names: Vec<String>,
 }
 
 impl<'defsrc> Dict<'defsrc> {
@@ -15,6 +77,14 @@ impl<'defsrc> Dict<'defsrc> {
     pub fn new() -> Dict<'defsrc> {
         Dict {
             inner: Trie::new(),
+            entries: Vec::new(),
+            headwords: HashMap::new(),
+            hanja_words: HashMap::new(),
+            hanja_chars: HashMap::new(),
+            readings: HashMap::new(),
+            exclusions: ExclusionOverlay::new(),
+            origins: HashMap::new(),
+            source_names: Vec::new(),
         }
     }
     
@@ -28,45 +98,383 @@ impl<'defsrc> Dict<'defsrc> {
         self.inner.find_shortest_match(key)
     }
     
+    /// Returns the definition of the exact key, if any.
+    pub fn get(&self, key: &str) -> Option<&Rc<Def<'defsrc>>> {
+        self.inner.get(&*nfc(key))
+    }
+    
+    /// Finds the longest definition at the given position of the text, that
+    /// isn't excluded there by a rule of the dictionary or of the overlay.
+    pub fn find_match<'n, 'k>(&'n self, text: &'k str, start: usize, 
+            overlay: &ExclusionOverlay) -> Option<(&'k str, &'n Rc<Def<'defsrc>>)> {
+        let rem = &text[start..];
+        if self.exclusions.is_empty() && overlay.is_empty() {
+            return self.inner.find_longest_match(rem);
+        }
+        let before = &text[..start];
+        self.inner.find_all_matches(rem).into_iter().rev().find(|&(key, def)| {
+            let after = &rem[key.len()..];
+            ! self.exclusions.excludes(key, def, before, after)
+                && ! overlay.excludes(key, def, before, after)
+        })
+    }
+    
     /// Inserts a definition.
     pub fn insert(&mut self, key: &str, value: Rc<Def<'defsrc>>) {
-        self.inner.insert(key, value)
+        let index = match self.entry_index(&value) {
+            Some(index) => index,
+            None => self.add_entry(value),
+        };
+        self.insert_key(&nfc(key), index, KeyOrigin::Inserted);
+    }
+    
+    /// Adds an entry for a new definition, indexes its headwords and hanja,
+    /// and returns its index.
+    fn add_entry(&mut self, def: Rc<Def<'defsrc>>) -> usize {
+        self.index_hanja(&def);
+        let index = self.entries.len();
+        for word in Some(&def.hangeul).into_iter().chain(&def.aliases) {
+            self.headwords.entry(word.to_string()).or_insert_with(Vec::new).push(index);
+        }
+        self.entries.push(Some(Entry { def, keys: Vec::new() }));
+        index
+    }
+    
+    /// Returns the index of the entry of a definition, if it is in the 
+    /// dictionary.
+    fn entry_index(&self, def: &Rc<Def<'defsrc>>) -> Option<usize> {
+        let indices = match self.headwords.get(&*def.hangeul) {
+            Some(indices) => indices,
+            None => return None,
+        };
+        indices.iter().cloned().find(|&i| match self.entries[i] {
+            Some(ref entry) => Rc::ptr_eq(&entry.def, def),
+            None => false,
+        })
+    }
+    
+    /// Inserts a normalized key for the definition of an entry, and 
+    /// remembers how it was made.
+    fn insert_key(&mut self, key: &str, index: usize, origin: KeyOrigin) {
+        let def = match self.entries[index] {
+            Some(ref mut entry) => {
+                entry.keys.push(key.to_string());
+                entry.def.clone()
+            }
+            None => return,
+        };
+        let replaced = self.inner.insert(key, def.clone());
+        self.origins.insert(key.to_string(), origin);
+        // A definition replaced under all of its keys is removed
+        if let Some(replaced) = replaced {
+            if ! Rc::ptr_eq(&replaced, &def) {
+                self.remove_if_unused(&replaced);
+            }
+        }
+    }
+    
+    /// Returns how a key of the dictionary was made from its definition.
+    pub fn key_origin(&self, key: &str) -> Option<&KeyOrigin> {
+        self.origins.get(&*nfc(key))
     }
     
     /// Removes a definition, if any.
     pub fn remove(&mut self, key: &str) -> Option<Rc<Def<'defsrc>>> {
-        self.inner.remove(key)
+        let key = nfc(key);
+        self.origins.remove(&*key);
+        let removed = self.inner.remove(&key);
+        if let Some(ref def) = removed {
+            self.remove_if_unused(def);
+        }
+        removed
+    }
+    
+    /// Removes the definitions that the function returns true for, given
+    /// their key.
+    /// Returns the number of removed keys.
+    pub fn remove_where<F: FnMut(&str, &Def<'defsrc>) -> bool>(&mut self, mut remove: F) -> usize {
+        let mut removed = Vec::new();
+        self.inner.retain(|key, def| {
+            if remove(key, &**def) {
+                removed.push((key.to_string(), def.clone()));
+                return false;
+            }
+            true
+        });
+        for &(ref key, ref def) in &removed {
+            self.origins.remove(key);
+            self.remove_if_unused(def);
+        }
+        removed.len()
+    }
+    
+    /// Removes the definition with the given headword (or alias), with every
+    /// key that was generated for it.
+    /// Returns the number of removed keys.
+    pub fn remove_definition(&mut self, headword: &str) -> usize {
+        let indices = self.headwords.get(&*nfc(headword)).cloned().unwrap_or_else(Vec::new);
+        indices.into_iter().map(|index| self.remove_entry(index)).sum()
+    }
+    
+    /// Removes the entry of a definition that no key leads to anymore.
+    fn remove_if_unused(&mut self, def: &Rc<Def<'defsrc>>) {
+        let index = match self.entry_index(def) {
+            Some(index) => index,
+            None => return,
+        };
+        let used = match self.entries[index] {
+            Some(ref entry) => entry.keys.iter().any(|key| self.leads_to(key, &entry.def)),
+            None => false,
+        };
+        if ! used {
+            self.remove_entry(index);
+        }
+    }
+    
+    /// Returns whether the key leads to the definition.
+    fn leads_to(&self, key: &str, def: &Rc<Def<'defsrc>>) -> bool {
+        self.inner.get(key).map(|found| Rc::ptr_eq(found, def)).unwrap_or(false)
+    }
+    
+    /// Removes an entry with the keys that still lead to its definition, and
+    /// removes the definition from every index.
+    /// This is the only place definitions are removed from the indices.
+    /// Returns the number of removed keys.
+    fn remove_entry(&mut self, index: usize) -> usize {
+        let entry = match self.entries[index].take() {
+            Some(entry) => entry,
+            None => return 0,
+        };
+        let mut removed = 0;
+        for key in &entry.keys {
+            if self.leads_to(key, &entry.def) {
+                self.inner.remove(key);
+                self.origins.remove(key);
+                removed += 1;
+            }
+        }
+        for word in Some(&entry.def.hangeul).into_iter().chain(&entry.def.aliases) {
+            if let Some(indices) = self.headwords.get_mut(&**word) {
+                indices.retain(|&i| i != index);
+            }
+        }
+        self.unindex_hanja(&entry.def);
+        removed
+    }
+    
+    /// Returns every definition in the dictionary once, in the order they
+    /// were added.
+    pub fn definitions(&self) -> Vec<&Rc<Def<'defsrc>>> {
+        self.entries.iter()
+            .filter_map(|entry| entry.as_ref().map(|entry| &entry.def))
+            .collect()
+    }
+    
+    /// Finds the definitions with a meaning that contains the query as whole
+    /// words, ignoring case. Definitions with a meaning equal to the query
+    /// come first, and the rest are sorted by their headword.
+    pub fn search_meanings(&self, query: &str) -> Vec<&Rc<Def<'defsrc>>> {
+        let words = query.split_whitespace().collect::<Vec<_>>();
+        if words.is_empty() {
+            return Vec::new();
+        }
+        let escaped = words.iter().map(|word| regex::escape(word)).collect::<Vec<_>>();
+        // A word boundary is only required next to a word character, so that
+        // queries like 'C++' or '(to) go' can be found.
+        let first = words[0].chars().next().unwrap();
+        let last = words[words.len() - 1].chars().last().unwrap();
+        let re = Regex::new(&format!(r"(?i){}{}{}", 
+                if is_word_char(first) { r"\b" } else { "" },
+                escaped.join(r"\s+"),
+                if is_word_char(last) { r"\b" } else { "" }))
+            .expect("search regex");
+        let query = words.join(" ").to_lowercase();
+        let is_exact = |def: &Def| def.meanings.iter().any(|m| m.trim().to_lowercase() == query);
+        let mut found = self.definitions().into_iter()
+            .filter(|def| def.meanings.iter().any(|m| re.is_match(m)))
+            .collect::<Vec<_>>();
+        found.sort_by(|a, b| {
+            (! is_exact(a), &a.hangeul).cmp(&(! is_exact(b), &b.hangeul))
+        });
+        found
+    }
+    
+    /// Returns the name of the word list the definition was read from, if
+    /// it was added with one.
+    pub fn source_of(&self, def: &Def<'defsrc>) -> Option<&str> {
+        def.source.and_then(|i| self.source_names.get(i)).map(|name| name.as_str())
+    }
+    
+    /// Finds the definitions written with the given hanja.
+    pub fn find_by_hanja(&self, hanja: &str) -> &[Rc<Def<'defsrc>>] {
+        self.hanja_words.get(hanja).map(|v| &v[..]).unwrap_or(&[])
+    }
+    
+    /// Finds the definitions whose hanja contain the given character.
+    pub fn find_by_hanja_char(&self, ch: char) -> &[Rc<Def<'defsrc>>] {
+        self.hanja_chars.get(&ch).map(|v| &v[..]).unwrap_or(&[])
+    }
+    
+    /// Returns the hanja read as the given syllable in the dictionary,
+    /// the most used first.
+    pub fn hanja_for_reading(&self, syllable: char) -> Vec<char> {
+        let mut found = self.readings.get(&syllable).cloned().unwrap_or_else(Vec::new);
+        found.sort_by(|a, b| b.1.cmp(&a.1));
+        found.into_iter().map(|(ch, _)| ch).collect()
+    }
+    
+    /// Adds the hanja of the definition to the hanja indices.
+    fn index_hanja(&mut self, def: &Rc<Def<'defsrc>>) {
+        for spelling in def.hanja.iter().filter(|h| h.text.chars().any(hanja::is_hanja)) {
+            let word = spelling.text.to_string();
+            self.hanja_words.entry(word).or_insert_with(Vec::new).push(def.clone());
+            for ch in spelling.text.chars().filter(|&ch| hanja::is_hanja(ch)) {
+                let defs = self.hanja_chars.entry(ch).or_insert_with(Vec::new);
+                if ! defs.iter().any(|d| Rc::ptr_eq(d, def)) {
+                    defs.push(def.clone());
+                }
+            }
+            for (syllable, ch) in hanja::align_readings(&def.hangeul, spelling) {
+                let found = self.readings.entry(syllable).or_insert_with(Vec::new);
+                if let Some(entry) = found.iter_mut().find(|e| e.0 == ch) {
+                    entry.1 += 1;
+                    continue;
+                }
+                found.push((ch, 1));
+            }
+        }
+    }
+    
+    /// Removes the hanja of the definition from the hanja indices.
+    fn unindex_hanja(&mut self, def: &Rc<Def<'defsrc>>) {
+        for spelling in def.hanja.iter().filter(|h| h.text.chars().any(hanja::is_hanja)) {
+            if let Some(defs) = self.hanja_words.get_mut(&*spelling.text) {
+                defs.retain(|d| ! Rc::ptr_eq(d, def));
+            }
+            for ch in spelling.text.chars().filter(|&ch| hanja::is_hanja(ch)) {
+                if let Some(defs) = self.hanja_chars.get_mut(&ch) {
+                    defs.retain(|d| ! Rc::ptr_eq(d, def));
+                }
+            }
+            for (syllable, ch) in hanja::align_readings(&def.hangeul, spelling) {
+                if let Some(found) = self.readings.get_mut(&syllable) {
+                    if let Some(entry) = found.iter_mut().find(|e| e.0 == ch) {
+                        entry.1 -= 1;
+                    }
+                    found.retain(|e| e.1 > 0);
+                }
+            }
+        }
+    }
+    
+    /// Reads the definitions of a word list and adds them to the dictionary.
+    pub fn add_word_list(&mut self, text: &'defsrc str) {
+        self.add_definitions(read_definitions(text));
+    }
+    
+    /// Excludes words from the translation. Rules without a context remove
+    /// the words they match from the dictionary.
+    pub fn add_exclusion(&mut self, rule: ExclusionRule) {
+        if ! rule.is_context_free() {
+            self.exclusions.add(rule);
+            return;
+        }
+        match rule.pattern {
+            // A plain word is both a key and a headword
+            Pattern::Key(ref word) => {
+                self.remove_definition(word);
+                self.remove(word);
+            }
+            Pattern::Headword(ref word) => {
+                self.remove_definition(word);
+            }
+            Pattern::Regex(_) => {
+                self.remove_where(|key, def| rule.matches_key(key, def));
+            }
+        }
+    }
+    
+    /// Adds the rules of an exclusion list, with one rule per line.
+    pub fn add_exclusions(&mut self, text: &str, path: &str) -> Result<(), KorError> {
+        for rule in read_exclusions(text, path)? {
+            self.add_exclusion(rule);
+        }
+        Ok(())
     }
     
     /// Adds the given definitions to the dictionary.
     /// Newer definitions of a word replace older ones.
+    /// The words are normalized to NFC, so that they match normalized text.
     pub fn add_definitions(&mut self, defs: Vec<Def<'defsrc>>) {
-        for def in defs {
+        self.add_definitions_with_source(defs, None);
+    }
+    
+    /// Adds the definitions of the named word list to the dictionary, like
+    /// 'add_definitions'.
+    pub fn add_definitions_from(&mut self, defs: Vec<Def<'defsrc>>, source_name: &str) {
+        self.source_names.push(source_name.to_string());
+        let source = self.source_names.len() - 1;
+        self.add_definitions_with_source(defs, Some(source));
+    }
+    
+    fn add_definitions_with_source(&mut self, defs: Vec<Def<'defsrc>>, source: Option<usize>) {
+        for mut def in defs {
+            nfc_in_place(&mut def.hangeul);
+            for alias in &mut def.aliases {
+                nfc_in_place(alias);
+            }
+            for spelling in &mut def.hanja {
+                nfc_in_place(&mut spelling.text);
+            }
+            def.source = source;
             let def = Rc::new(def);
-            for key in Some(def.hangeul.clone()).iter().chain(def.aliases.iter()) {
+            let index = self.add_entry(def.clone());
+            let keys = Some(def.hangeul.clone()).into_iter().chain(def.aliases.iter().cloned())
+                .collect::<Vec<_>>();
+            for (i, key) in keys.iter().enumerate() {
                 if (&key).ends_with("하다") {
-                    self.inner.insert(&key[..key.len() - "하다".len()], def.clone());
+                    let origin = KeyOrigin::HadaStem(key.to_string());
+                    self.insert_key(&key[..key.len() - "하다".len()], index, origin);
                 } else if (&key).ends_with("다") {
-                    simple_conjugations_iter(&key[..key.len() - "다".len()], |conj| {
-                        self.inner.insert(conj, def.clone());
+                    simple_conjugations_iter(&key[..key.len() - "다".len()], |conj, rule| {
+                        let origin = KeyOrigin::Conjugation { word: key.to_string(), rule };
+                        self.insert_key(conj, index, origin);
                     });
+                } else if i == 0 {
+                    self.insert_key(&key, index, KeyOrigin::Headword);
                 } else {
-                    self.inner.insert(&key, def.clone());
+                    self.insert_key(&key, index, KeyOrigin::Alias(key.to_string()));
                 }
             }
+            // Hanja forms are stored without markers, so that a stem like
+            // '幼稚' is found in '幼稚하다'.
+            for spelling in def.hanja.iter().filter(|h| h.text.chars().any(hanja::is_hanja)) {
+                let origin = KeyOrigin::Hanja(spelling.text.to_string());
+                self.insert_key(&spelling.text, index, origin);
+            }
         }
     }
 }
 
+/// Returns whether the character is part of a word for a regex '\b'.
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
 /// Conjugates the given stem to a list of common forms in Korean.
-/// The conjugated versions are then 'sent' to the handler. 
-fn simple_conjugations_iter<F: FnMut(&str)>(stem: &str, mut handle_conj: F) {
+/// The conjugated versions are then 'sent' to the handler, with the name of
+/// the rule that made them.
+fn simple_conjugations_iter<F: FnMut(&str, &'static str)>(stem: &str, mut handle_conj: F) {
     use hangeul2::Vowel::*;
     use hangeul2::Final::*;
-    handle_conj(stem);
-    let last = stem.chars().last().unwrap();
+    let last = match stem.chars().last() {
+        Some(last) => last,
+        // Words like '다' have no stem to conjugate
+        None => return,
+    };
+    handle_conj(stem, "stem");
     if ! is_hangeul(last) {
-        println!("LAST IS NOT HANGEUL!: {:?}", stem);
+        warn!("Can't conjugate the stem {:?}, which doesn't end in hangeul", stem);
         return;
     }
     let prefix = {
@@ -75,42 +483,42 @@ fn simple_conjugations_iter<F: FnMut(&str)>(stem: &str, mut handle_conj: F) {
         chars.collect::<String>()
     };
     macro_rules! push_with_last {
-        ($block:expr) => {{
+        ($block:expr, $rule:expr) => {{
             let mut text = prefix.clone();
             text.push($block.into());
-            handle_conj(&text);
+            handle_conj(&text, $rule);
         }}
     }
 
     let block = Block::new(last).unwrap();
     match block.final_ {
         Empty => {
-            push_with_last!(block.with_final(N)); // past/descr
-            push_with_last!(block.with_final(L)); // fut
-            push_with_last!(block.with_final(B)); // (sy)bnida ending
+            push_with_last!(block.with_final(N), "-ㄴ (past/descriptive)");
+            push_with_last!(block.with_final(L), "-ㄹ (future)");
+            push_with_last!(block.with_final(B), "-ㅂ니다 (formal)");
             
             match block.vowel {
                 A => {
-                    push_with_last!(block.with_vowel(Ae));
-                    push_with_last!(block.with_vowel(Ae).with_final(Ss));
+                    push_with_last!(block.with_vowel(Ae), "-애 (contracted)");
+                    push_with_last!(block.with_vowel(Ae).with_final(Ss), "-앴 (contracted past)");
                 }
                 I => {
-                    push_with_last!(block.with_vowel(Yeo));
-                    push_with_last!(block.with_vowel(Yeo).with_final(Ss));
+                    push_with_last!(block.with_vowel(Yeo), "-여 (contracted)");
+                    push_with_last!(block.with_vowel(Yeo).with_final(Ss), "-였 (contracted past)");
                 }
                 Y => {
-                    push_with_last!(block.with_vowel(Eo));
-                    push_with_last!(block.with_vowel(Eo).with_final(Ss));
+                    push_with_last!(block.with_vowel(Eo), "-어 (contracted)");
+                    push_with_last!(block.with_vowel(Eo).with_final(Ss), "-었 (contracted past)");
                 }
                 _ => {
-                    push_with_last!(block.with_final(L));
-                    push_with_last!(block.with_final(Ss));
+                    push_with_last!(block.with_final(L), "-ㄹ (future)");
+                    push_with_last!(block.with_final(Ss), "-ㅆ (past)");
                 }
             }
         }
         L => {
-            push_with_last!(block.with_final(Empty));
-            push_with_last!(block.with_final(N));
+            push_with_last!(block.with_final(Empty), "ㄹ dropped");
+            push_with_last!(block.with_final(N), "ㄹ dropped, -ㄴ (descriptive)");
         }
         B => {
             let mut u_end = prefix.clone();
@@ -122,20 +530,87 @@ fn simple_conjugations_iter<F: FnMut(&str)>(stem: &str, mut handle_conj: F) {
             let mut weoss_end = u_end.clone();
             
             u_end.push(Block::from_parts(Initial::Ieung, U, Empty).into());
-            handle_conj(&u_end);
+            handle_conj(&u_end, "ㅂ irregular -우");
             
             un_end.push(Block::from_parts(Initial::Ieung, U, N).into());
-            handle_conj(&un_end);
+            handle_conj(&un_end, "ㅂ irregular -운 (descriptive)");
             
             ul_end.push(Block::from_parts(Initial::Ieung, U, L).into());
-            handle_conj(&ul_end);
+            handle_conj(&ul_end, "ㅂ irregular -울 (future)");
             
             weo_end.push(Block::from_parts(Initial::Ieung, Weo, Empty).into());
-            handle_conj(&weo_end);
+            handle_conj(&weo_end, "ㅂ irregular -워 (contracted)");
             
             weoss_end.push(Block::from_parts(Initial::Ieung, Weo, Ss).into());
-            handle_conj(&weoss_end);
+            handle_conj(&weoss_end, "ㅂ irregular -웠 (past)");
         }
         _ => {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excluding_a_headword_removes_its_forms() {
+        let mut dict = Dict::new();
+        dict.add_word_list("가다\n  to go\n오다\n  to come\n");
+        for form in &["가", "간", "갈", "갑", "개", "갰"] {
+            assert!(dict.get(form).is_some(), "{}", form);
+        }
+        dict.add_exclusion(ExclusionRule::parse("가다").unwrap());
+        for form in &["가", "간", "갈", "갑", "개", "갰"] {
+            assert!(dict.get(form).is_none(), "{}", form);
+            assert!(dict.key_origin(form).is_none(), "{}", form);
+        }
+        assert!(dict.headwords.get("가다").map(|v| v.is_empty()).unwrap_or(true));
+        assert!(dict.get("온").is_some());
+        assert_eq!(dict.definitions().len(), 1);
+    }
+
+    #[test]
+    fn replaced_definitions_are_unindexed() {
+        let mut dict = Dict::new();
+        dict.add_word_list("유치 (幼稚)\n  childishness\n");
+        dict.add_word_list("유치 (幼稚)\n  immaturity\n");
+        let found = dict.find_by_hanja("幼稚");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].meanings, vec!["immaturity"]);
+        assert_eq!(dict.find_by_hanja_char('幼').len(), 1);
+        assert_eq!(dict.headwords["유치"].len(), 1);
+        assert_eq!(dict.definitions().len(), 1);
+        assert_eq!(dict.hanja_for_reading('유'), vec!['幼']);
+    }
+
+    #[test]
+    fn meanings_are_searched_as_words() {
+        let mut dict = Dict::new();
+        dict.add_word_list("가다\n  (to) go\n씨\n  Mr.\n  Ms.\n코딩\n  coding in C++\n고\n  gopher\n");
+        let headwords = |query: &str| dict.search_meanings(query).iter()
+            .map(|def| def.hangeul.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(headwords("(to) go"), vec!["가다"]);
+        assert_eq!(headwords("go"), vec!["가다"]);
+        assert_eq!(headwords("mr."), vec!["씨"]);
+        assert_eq!(headwords("C++"), vec!["코딩"]);
+        assert_eq!(headwords("coding  IN"), vec!["코딩"]);
+        assert!(headwords("cod").is_empty());
+    }
+
+    #[test]
+    fn inserted_definitions_keep_other_readings() {
+        let mut dict = Dict::new();
+        dict.add_word_list("유치하다 (幼稚-)\n  to be childish\n");
+        assert_eq!(dict.hanja_for_reading('유'), vec!['幼']);
+        let def = read_definitions("유치원 (幼稚園)\n  kindergarten\n").remove(0);
+        dict.insert("유치원", Rc::new(def));
+        assert_eq!(dict.find_by_hanja("幼稚園").len(), 1);
+        assert!(dict.remove("유치원").is_some());
+        assert!(dict.find_by_hanja("幼稚園").is_empty());
+        assert_eq!(dict.hanja_for_reading('유'), vec!['幼']);
+        assert_eq!(dict.hanja_for_reading('치'), vec!['稚']);
+        assert!(dict.hanja_for_reading('원').is_empty());
+        assert_eq!(dict.find_by_hanja_char('幼').len(), 1);
+    }
+}
@@ -0,0 +1,211 @@
+//! Module for showing the differences between two versions of a text.
+
+/// A step in turning the old lines into the new ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    /// A line kept at the given old and new indices.
+    Keep(usize, usize),
+    /// The old line at the given index was removed.
+    Delete(usize),
+    /// The new line at the given index was added.
+    Insert(usize),
+}
+
+/// Finds the shortest list of edits between the lines, using Myers'
+/// algorithm.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    use self::Edit::*;
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let offset = n + m;
+    let mut v = vec![0isize; 2 * (n + m) as usize + 2];
+    // The diagonals -d..=d of 'v' before each number of edits d, which are
+    // all that the walk back needs
+    let mut trace = Vec::new();
+
+    // Find the furthest reaching paths for each number of edits
+    'search: for d in 0..(n + m + 1) {
+        trace.push(v[(offset - d) as usize..(offset + d + 1) as usize].to_vec());
+        let mut k = -d;
+        while k <= d {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+            k += 2;
+        }
+    }
+
+    // Walk the paths backwards to find the edits
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, diagonals) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let (prev_x, prev_y) = if d == 0 {
+            // The path starts at the top left corner
+            (0, 0)
+        } else {
+            let v = |k: isize| diagonals[(k + d) as usize];
+            let prev_k = if k == -d || (k != d && v(k - 1) < v(k + 1)) { k + 1 } else { k - 1 };
+            let prev_x = v(prev_k);
+            (prev_x, prev_x - prev_k)
+        };
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Keep(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                edits.push(Insert(prev_y as usize));
+            } else {
+                edits.push(Delete(prev_x as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    edits.reverse();
+    edits
+}
+
+/// Returns the start of a hunk range in the unified format.
+fn hunk_start(start: usize, count: usize) -> usize {
+    if count == 0 { start } else { start + 1 }
+}
+
+/// Creates a unified diff between the old and new text, with the given
+/// number of unchanged lines around each change.
+/// Returns an empty string if the texts have the same lines.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str,
+        context: usize) -> String {
+    use self::Edit::*;
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+    let edits = diff_lines(&old_lines, &new_lines);
+
+    let changes = edits.iter().enumerate()
+        .filter(|&(_, edit)| match *edit { Keep(..) => false, _ => true })
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // The old and new line positions before each edit
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for edit in &edits {
+        positions.push((old_pos, new_pos));
+        match *edit {
+            Keep(..) => { old_pos += 1; new_pos += 1; }
+            Delete(_) => { old_pos += 1; }
+            Insert(_) => { new_pos += 1; }
+        }
+    }
+    positions.push((old_pos, new_pos));
+
+    let mut diff = format!("--- {}\n+++ {}\n", old_name, new_name);
+    let mut c = 0;
+    while c < changes.len() {
+        // Group the changes that are close enough to share their context
+        let start = changes[c].saturating_sub(context);
+        let mut last = changes[c];
+        c += 1;
+        while c < changes.len() && changes[c] - last <= 2 * context + 1 {
+            last = changes[c];
+            c += 1;
+        }
+        let end = ::std::cmp::min(last + context + 1, edits.len());
+
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        diff.push_str(&format!("@@ -{},{} +{},{} @@\n",
+            hunk_start(old_start, old_end - old_start), old_end - old_start,
+            hunk_start(new_start, new_end - new_start), new_end - new_start));
+        for edit in &edits[start..end] {
+            match *edit {
+                Keep(i, _) => { diff.push(' '); diff.push_str(old_lines[i]); }
+                Delete(i) => { diff.push('-'); diff.push_str(old_lines[i]); }
+                Insert(i) => { diff.push('+'); diff.push_str(new_lines[i]); }
+            }
+            diff.push('\n');
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::Edit::*;
+
+    const LINES: &str = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+
+    #[test]
+    fn empty_inputs() {
+        assert_eq!(diff_lines(&[], &[]), vec![]);
+        assert_eq!(unified_diff("", "", "old", "new", 3), "");
+    }
+
+    #[test]
+    fn identical_inputs() {
+        let lines = ["a", "b", "c"];
+        assert_eq!(diff_lines(&lines, &lines), vec![Keep(0, 0), Keep(1, 1), Keep(2, 2)]);
+        assert_eq!(unified_diff(LINES, LINES, "old", "new", 3), "");
+    }
+
+    #[test]
+    fn pure_insertion() {
+        assert_eq!(diff_lines(&[], &["a", "b"]), vec![Insert(0), Insert(1)]);
+        assert_eq!(diff_lines(&["a"], &["a", "b"]), vec![Keep(0, 0), Insert(1)]);
+        assert_eq!(unified_diff("", "a\nb\n", "old", "new", 3),
+            "--- old\n+++ new\n@@ -0,0 +1,2 @@\n+a\n+b\n");
+    }
+
+    #[test]
+    fn pure_deletion() {
+        assert_eq!(diff_lines(&["a", "b"], &[]), vec![Delete(0), Delete(1)]);
+        assert_eq!(diff_lines(&["a", "b"], &["b"]), vec![Delete(0), Keep(1, 0)]);
+        assert_eq!(unified_diff("a\nb\n", "", "old", "new", 3),
+            "--- old\n+++ new\n@@ -1,2 +0,0 @@\n-a\n-b\n");
+    }
+
+    #[test]
+    fn replaced_line() {
+        assert_eq!(diff_lines(&["a"], &["b"]), vec![Delete(0), Insert(0)]);
+    }
+
+    #[test]
+    fn hunk_context() {
+        let new = LINES.replace("5\n", "five\n");
+        assert_eq!(unified_diff(LINES, &new, "old", "new", 2),
+            "--- old\n+++ new\n@@ -3,5 +3,5 @@\n 3\n 4\n-5\n+five\n 6\n 7\n");
+        assert_eq!(unified_diff(LINES, &new, "old", "new", 0),
+            "--- old\n+++ new\n@@ -5,1 +5,1 @@\n-5\n+five\n");
+    }
+
+    #[test]
+    fn hunk_merging() {
+        let new = LINES.replace("2\n", "two\n").replace("6\n", "six\n");
+        let separate = unified_diff(LINES, &new, "old", "new", 1);
+        assert_eq!(separate.matches("@@ -").count(), 2);
+        assert!(separate.contains("@@ -1,3 +1,3 @@\n 1\n-2\n+two\n 3\n"));
+        assert!(separate.contains("@@ -5,3 +5,3 @@\n 5\n-6\n+six\n 7\n"));
+        let merged = unified_diff(LINES, &new, "old", "new", 2);
+        assert_eq!(merged.matches("@@ -").count(), 1);
+        assert!(merged.starts_with("--- old\n+++ new\n@@ -1,8 +1,8 @@\n 1\n-2\n+two\n"));
+    }
+}
@@ -0,0 +1,380 @@
+//! Module for translating and cleaning whole documents.
+
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use std::collections::HashMap;
+use std_unicode::str::UnicodeStr;
+use dict::Dict;
+use translate::{TranslateOptions, TranslationPart, translate_iter_excluding, 
+    translate_with_options, translate_writer};
+use config::Markers;
+use exclusion::ExclusionOverlay;
+use normalize::nfc;
+use coverage::{line_coverage, percentage};
+use workfile::{WorkFile, Segment, Item, Line, LineKind, UNREVIEWED_MATCH};
+
+/// The prefix of automatically translated lines in line mode.
+pub const AUTO_PREFIX:   &str = r"->";
+/// The prefix of lines for manual translations in line mode.
+pub const MANUAL_PREFIX: &str = r"-|";
+/// The prefix of comment lines in line mode.
+pub const COMMENT_PREFIX: &str = r"-#";
+/// The number of blank manual translation lines placed under each line.
+pub const MANUAL_LINES: usize = 3;
+
+/// How a document is translated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranslationMode {
+    /// The text is translated as it is.
+    Normal,
+    /// Each line is followed by its translation and blank lines for a
+    /// manual translation.
+    LineByLineWithSpace,
+    /// A line mode document is translated again, keeping the manual
+    /// translations.
+    Retranslate,
+}
+
+/// Returns the automatic translation of a source line, if it changed it.
+fn auto_translation(line: &str, dict: &Dict, options: &TranslateOptions) -> Option<String> {
+    let translated = translate_with_options(line, dict, options);
+    if translated != line { Some(translated) } else { None }
+}
+
+/// Adds manual lines to a segment, filled in with the best match from the
+/// translation memory if there is one, or else left empty. Inexact matches
+/// are marked as unreviewed.
+fn add_manual_lines(segment: &mut Segment, options: &TranslateOptions, markers: &Markers) {
+    let found = options.memory.and_then(|memory| {
+        memory.find(&segment.source, options.min_match_score)
+    });
+    if let Some(found) = found {
+        let comment = if found.score == 100 {
+            String::from("Memory match (100%)")
+        } else {
+            format!("{} ({}%): {}", UNREVIEWED_MATCH, found.score, found.entry.source)
+        };
+        segment.lines.push(Line::new(LineKind::Comment, &comment, markers));
+        let lines = found.entry.target.lines().collect::<Vec<_>>();
+        segment.set_manual_translation(&lines, markers);
+    } else {
+        segment.add_manual_placeholders(markers);
+    }
+}
+
+/// Translates the document read from the input, and writes the result to
+/// the output. Line mode documents use the given markers, and their manual
+/// lines are filled in from the translation memory of the options.
+/// Exclusion directives in the document apply to the lines after them, and
+/// are written unchanged.
+pub fn translate_document(input: &mut BufRead, dict: &Dict, options: &TranslateOptions,
+        mode: TranslationMode, markers: &Markers, out: &mut Write) -> io::Result<()> {
+    use self::TranslationMode::*;
+    let mut options = options.clone();
+    let prefix = &markers.comment_prefix;
+    match mode {
+        Normal => {
+            let mut line = String::new();
+            while input.read_line(&mut line)? != 0 {
+                if options.exclusions.apply_directive(&line, prefix) {
+                    out.write_all(line.as_bytes())?;
+                } else {
+                    translate_writer(&line, dict, &options, out)?;
+                }
+                line.clear();
+            }
+        }
+        LineByLineWithSpace => {
+            for (i, line) in input.lines().enumerate() {
+                let line = line?;
+                if line.is_whitespace() || options.exclusions.apply_directive(&line, prefix) {
+                    writeln!(out, "{}", line)?;
+                    continue;
+                }
+                if line.starts_with(prefix.as_str()) {
+                    warn!("Line {}: {:?} starts with the comment prefix {:?}, so it will be \
+                        read back as a comment; use another prefix to keep it as a source line", 
+                        i + 1, line, prefix);
+                }
+                let mut segment = Segment::new(&line);
+                let translated = auto_translation(&line, dict, &options);
+                segment.set_auto_translation(translated.as_ref().map(|s| s.as_str()), markers);
+                add_manual_lines(&mut segment, &options, markers);
+                segment.write(out)?;
+            }
+        }
+        Retranslate => {
+            let mut work_file = WorkFile::read(input, markers)?;
+            for item in &mut work_file.items {
+                match *item {
+                    Item::Segment(ref mut segment) => {
+                        let translated = auto_translation(&segment.source, dict, &options);
+                        segment.set_auto_translation(translated.as_ref().map(|s| s.as_str()), 
+                            markers);
+                        // New source lines get manual lines too
+                        if segment.manual_lines().next().is_none() {
+                            add_manual_lines(segment, &options, markers);
+                        }
+                        for line in segment.lines_of_kind(LineKind::Comment) {
+                            options.exclusions.apply_directive(&line.raw, prefix);
+                        }
+                    }
+                    Item::Line(ref line) => {
+                        options.exclusions.apply_directive(&line.raw, prefix);
+                    }
+                    Item::Blank(_) => {}
+                }
+            }
+            work_file.write(out)?;
+        }
+    }
+    out.flush()
+}
+
+/// Writes how each word of a line was translated: the text it matched, the
+/// dictionary key, the headword, how the key was made from it, and where the
+/// definition was read from.
+fn explain_line(line_number: usize, line: &str, dict: &Dict, exclusions: &ExclusionOverlay, 
+        out: &mut Write) -> io::Result<()> {
+    writeln!(out, "Line {}: {}", line_number, line.trim())?;
+    let mut result = Ok(());
+    translate_iter_excluding(line, dict, exclusions, |part| {
+        if let TranslationPart::Translated(src, def) = part {
+            if result.is_err() {
+                return;
+            }
+            let key = nfc(src);
+            let origin = dict.key_origin(&key)
+                .map(|origin| origin.to_string())
+                .unwrap_or_else(|| String::from("unknown origin"));
+            let source = match dict.source_of(def) {
+                Some(name) => format!("{}:{}", name, def.line),
+                None if def.line > 0 => format!("line {}", def.line),
+                None => String::from("unknown source"),
+            };
+            result = writeln!(out, "  {:?} -> key {:?}, headword {:?} ({}), from {}", 
+                src, key, def.hangeul, origin, source);
+        }
+    });
+    result
+}
+
+/// Writes how the lines of the document read from the input would be
+/// translated, instead of their translation. Line mode documents only have
+/// their source lines explained.
+pub fn explain_document(input: &mut BufRead, dict: &Dict, mode: TranslationMode, 
+        markers: &Markers, out: &mut Write) -> io::Result<()> {
+    let mut exclusions = ExclusionOverlay::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.is_whitespace() || exclusions.apply_directive(&line, &markers.comment_prefix) {
+            continue;
+        }
+        let is_marked = [&markers.auto_prefix, &markers.manual_prefix, &markers.comment_prefix]
+            .iter().any(|prefix| line.starts_with(prefix.as_str()));
+        if mode == TranslationMode::Retranslate && is_marked {
+            continue;
+        }
+        explain_line(i + 1, &line, dict, &exclusions, out)?;
+    }
+    out.flush()
+}
+
+/// How a cleaned document is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CleanLayout {
+    /// Only the translations are written.
+    Translation,
+    /// Each source line is followed by its translation.
+    Alternating,
+    /// Each source line and its translation are written on the same line,
+    /// separated by a tab.
+    SideBySide,
+}
+
+impl FromStr for CleanLayout {
+    type Err = String;
+    fn from_str(s: &str) -> Result<CleanLayout, String> {
+        match s.trim().to_lowercase().as_str() {
+            "translation" => Ok(CleanLayout::Translation),
+            "alternating" => Ok(CleanLayout::Alternating),
+            "side-by-side" => Ok(CleanLayout::SideBySide),
+            other => Err(format!("Unknown layout: {:?} (expected 'translation', 'alternating' \
+                or 'side-by-side')", other)),
+        }
+    }
+}
+
+/// Options for cleaning a line mode document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanOptions {
+    pub layout: CleanLayout,
+    /// Whether lines without a manual translation use their automatic
+    /// translation instead.
+    pub use_auto: bool,
+}
+
+impl Default for CleanOptions {
+    fn default() -> CleanOptions {
+        CleanOptions { layout: CleanLayout::Translation, use_auto: false }
+    }
+}
+
+/// Returns the lines written as the translation of a segment.
+fn clean_translation(segment: &Segment, markers: &Markers, options: &CleanOptions) 
+        -> Vec<String> {
+    let translated = segment.is_translated();
+    if ! translated && options.use_auto {
+        if let Some(line) = segment.auto_line() {
+            return vec![line.content().to_string()];
+        }
+    }
+    // Next to the source, empty placeholders are only kept when nothing was 
+    // translated
+    let keep_empty = options.layout == CleanLayout::Translation || ! translated;
+    segment.manual_lines()
+        .filter(|line| keep_empty || ! line.is_empty())
+        .map(|line| clean_line(line, markers))
+        .collect()
+}
+
+/// Returns the text written for a manual line.
+fn clean_line(line: &Line, markers: &Markers) -> String {
+    if line.is_empty() {
+        // Indicate that something wasn't translated
+        format!("{} ", markers.manual_prefix)
+    } else {
+        line.content().to_string()
+    }
+}
+
+/// Writes the translations of a line mode document to the output, with
+/// their source lines if the layout includes them.
+pub fn clean_document(work_file: &WorkFile, markers: &Markers, options: &CleanOptions, 
+        out: &mut Write) -> io::Result<()> {
+    use self::CleanLayout::*;
+    for item in &work_file.items {
+        match *item {
+            Item::Blank(ref raw) => writeln!(out, "{}", raw.trim())?,
+            Item::Segment(ref segment) => {
+                let translation = clean_translation(segment, markers, options);
+                match options.layout {
+                    Translation => {
+                        for line in translation {
+                            writeln!(out, "{}", line)?;
+                        }
+                    }
+                    Alternating => {
+                        writeln!(out, "{}", segment.source)?;
+                        for line in translation {
+                            writeln!(out, "{}", line)?;
+                        }
+                    }
+                    SideBySide => {
+                        writeln!(out, "{}\t{}", segment.source, translation.join(" "))?;
+                    }
+                }
+            }
+            Item::Line(ref line) if line.kind == LineKind::Manual => {
+                writeln!(out, "{}", clean_line(line, markers))?;
+            }
+            Item::Line(_) => {}
+        }
+    }
+    out.flush()
+}
+
+/// Returns the line numbers of the source lines that would be left
+/// untranslated by cleaning the document with the given options.
+pub fn untranslated_lines(work_file: &WorkFile, options: &CleanOptions) -> Vec<usize> {
+    work_file.numbered_segments()
+        .filter(|&(_, segment)| {
+            ! segment.is_translated() && ! (options.use_auto && segment.auto_line().is_some())
+        })
+        .map(|(number, _)| number)
+        .collect()
+}
+
+/// The translation progress of a line mode document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkFileStatus {
+    /// The number of (non-blank) source lines.
+    pub source_lines: usize,
+    /// The source lines with a manual translation.
+    pub translated_lines: usize,
+    /// The source lines filled in with a memory match that hasn't been
+    /// reviewed.
+    pub unreviewed_lines: usize,
+    /// The source lines with only empty manual lines.
+    pub placeholder_lines: usize,
+    /// The source lines without any manual lines.
+    pub missing_lines: usize,
+    /// The number of Korean words in the source lines.
+    pub words: usize,
+    /// The Korean words whose stem is translated.
+    pub covered_words: usize,
+    /// The Korean words with a translated part, but an untranslated stem.
+    pub partly_covered_words: usize,
+}
+
+impl WorkFileStatus {
+    /// Counts the manual lines found for a source line.
+    fn add_source_line(&mut self, segment: &Segment) {
+        self.source_lines += 1;
+        if segment.is_translated() {
+            self.translated_lines += 1;
+        } else if segment.is_unreviewed() {
+            self.unreviewed_lines += 1;
+        } else if segment.manual_lines().next().is_some() {
+            self.placeholder_lines += 1;
+        } else {
+            self.missing_lines += 1;
+        }
+    }
+
+    /// Counts the Korean words of the line, and how many of them are covered
+    /// by the dictionary, like 'line_coverage'.
+    fn add_words(&mut self, line: &str, dict: &Dict, exclusions: &ExclusionOverlay) {
+        let coverage = line_coverage(line, dict, exclusions, &mut HashMap::new());
+        self.words += coverage.words;
+        self.covered_words += coverage.covered_words;
+        self.partly_covered_words += coverage.partly_covered_words;
+    }
+
+    /// Returns the percentage of source lines with a manual translation.
+    pub fn translated_percentage(&self) -> f64 {
+        percentage(self.translated_lines, self.source_lines)
+    }
+
+    /// Returns the percentage of words with a definition in the dictionary.
+    pub fn coverage_percentage(&self) -> f64 {
+        percentage(self.covered_words, self.words)
+    }
+}
+
+/// Finds how much of a line mode document has been translated, and how much
+/// of its source text is covered by the dictionary, with the exclusion
+/// directives of the document applied like in a retranslation.
+pub fn work_file_status(input: &mut BufRead, dict: &Dict, markers: &Markers) 
+        -> io::Result<WorkFileStatus> {
+    let work_file = WorkFile::read(input, markers)?;
+    let mut status = WorkFileStatus::default();
+    let mut exclusions = ExclusionOverlay::new();
+    let prefix = &markers.comment_prefix;
+    for item in &work_file.items {
+        match *item {
+            Item::Segment(ref segment) => {
+                status.add_source_line(segment);
+                status.add_words(&segment.source, dict, &exclusions);
+                for line in segment.lines_of_kind(LineKind::Comment) {
+                    exclusions.apply_directive(&line.raw, prefix);
+                }
+            }
+            Item::Line(ref line) => {
+                exclusions.apply_directive(&line.raw, prefix);
+            }
+            Item::Blank(_) => {}
+        }
+    }
+    Ok(status)
+}
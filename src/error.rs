@@ -0,0 +1,133 @@
+//! Module for the errors that can happen when loading and processing files.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+
+/// The kinds of files used by the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    WordList,
+    ExclusionList,
+    HanjaTable,
+    Config,
+    Memory,
+    Document,
+    Output,
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::FileKind::*;
+        let name = match *self {
+            WordList => "word list",
+            ExclusionList => "exclusion list",
+            HanjaTable => "hanja table",
+            Config => "configuration file",
+            Memory => "translation memory",
+            Document => "document",
+            Output => "output file",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// An error with the program's arguments, files or data.
+#[derive(Debug)]
+pub enum KorError {
+    /// An invalid command line argument.
+    Args(String),
+    /// A file could not be opened, read or written.
+    Io { kind: FileKind, path: String, err: io::Error },
+    /// A file could not be parsed.
+    Parse { kind: FileKind, path: String, line: usize, message: String },
+    /// A document has lines without a translation.
+    Untranslated { path: String, lines: Vec<usize> },
+}
+
+impl KorError {
+    /// Creates an IO error for the given file.
+    pub fn io(kind: FileKind, path: &str, err: io::Error) -> KorError {
+        KorError::Io { kind, path: path.to_string(), err }
+    }
+
+    /// Returns the exit code the program uses for this error.
+    ///
+    /// 1: Invalid arguments.
+    /// 2: A word list, exclusion list, hanja table, configuration file or
+    ///    translation memory could not be read.
+    /// 3: A document could not be read.
+    /// 4: The output could not be written.
+    /// 5: A file could not be parsed.
+    /// 7: A document has untranslated lines.
+    pub fn exit_code(&self) -> i32 {
+        use self::KorError::*;
+        use self::FileKind::*;
+        match *self {
+            Args(_) => 1,
+            Io { kind: WordList, .. }
+            | Io { kind: ExclusionList, .. }
+            | Io { kind: HanjaTable, .. }
+            | Io { kind: Config, .. }
+            | Io { kind: Memory, .. } => 2,
+            Io { kind: Document, .. } => 3,
+            Io { kind: Output, .. } => 4,
+            Parse { .. } => 5,
+            Untranslated { .. } => 7,
+        }
+    }
+}
+
+impl fmt::Display for KorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::KorError::*;
+        match *self {
+            Args(ref message) => write!(f, "{}", message),
+            Io { kind, ref path, ref err } => {
+                write!(f, "Could not access {} {:?}: {}", kind, path, err)
+            }
+            Parse { kind, ref path, line, ref message } => {
+                write!(f, "Invalid {} {:?} (line {}): {}", kind, path, line, message)
+            }
+            Untranslated { ref path, ref lines } => {
+                write!(f, "{} line(s) of {:?} are not translated (line", lines.len(), path)?;
+                for (i, line) in lines.iter().take(10).enumerate() {
+                    write!(f, "{}{}", if i == 0 { " " } else { ", " }, line)?;
+                }
+                if lines.len() > 10 {
+                    write!(f, ", ...")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Error for KorError {
+    fn description(&self) -> &str {
+        use self::KorError::*;
+        match *self {
+            Args(_) => "invalid arguments",
+            Io { .. } => "could not access file",
+            Parse { .. } => "could not parse file",
+            Untranslated { .. } => "untranslated lines",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            KorError::Io { ref err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the file at the given path to a string.
+pub fn read_file(path: &str, kind: FileKind) -> Result<String, KorError> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|e| KorError::io(kind, path, e))?;
+    Ok(text)
+}
@@ -0,0 +1,282 @@
+//! Module for exchanging line mode documents with translation tools, as
+//! translation memories (TMX) or bilingual documents (XLIFF).
+
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::collections::HashMap;
+use regex::Regex;
+use error::{KorError, FileKind};
+use config::Markers;
+use workfile::{WorkFile, Segment};
+
+lazy_static! {
+    static ref RE_TRANS_UNIT: Regex = {
+        Regex::new(r"(?s)<trans-unit\b([^>]*)>(.*?)</trans-unit>").expect("RE_TRANS_UNIT")
+    };
+    static ref RE_ID: Regex = {
+        // Not 'xml:id', which is another attribute
+        Regex::new(r#"(?:^|\s)id\s*=\s*["']([^"']*)["']"#).expect("RE_ID")
+    };
+    static ref RE_SOURCE: Regex = {
+        Regex::new(r"(?s)<source\b[^>]*>(.*?)</source>").expect("RE_SOURCE")
+    };
+    static ref RE_TARGET: Regex = {
+        Regex::new(r"(?s)<target\b[^>]*>(.*?)</target>").expect("RE_TARGET")
+    };
+    static ref RE_TAG: Regex = {
+        Regex::new(r"<[^>]*>").expect("RE_TAG")
+    };
+    static ref RE_ENTITY: Regex = {
+        Regex::new(r"&(#x[0-9a-fA-F]+|#[0-9]+|[a-z]+);").expect("RE_ENTITY")
+    };
+}
+
+/// A file format used by translation tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExchangeFormat {
+    /// A TMX 1.4 translation memory with the translated lines.
+    Tmx,
+    /// An XLIFF 1.2 document with all the source lines.
+    Xliff,
+}
+
+impl FromStr for ExchangeFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<ExchangeFormat, String> {
+        match s.trim().to_lowercase().as_str() {
+            "tmx" => Ok(ExchangeFormat::Tmx),
+            "xliff" | "xlf" => Ok(ExchangeFormat::Xliff),
+            other => Err(format!("Unknown format: {:?} (expected 'tmx' or 'xliff')", other)),
+        }
+    }
+}
+
+/// Escapes the text for use in XML content and attributes.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Replaces the XML entities of the text with their characters.
+fn unescape(text: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut last = 0;
+    for caps in RE_ENTITY.captures_iter(text) {
+        let whole = caps.get(0).unwrap();
+        let name = caps.get(1).unwrap().as_str();
+        let ch = match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if name.starts_with("#x") => {
+                u32::from_str_radix(&name[2..], 16).ok().and_then(::std::char::from_u32)
+            }
+            _ if name.starts_with("#") => {
+                name[1..].parse::<u32>().ok().and_then(::std::char::from_u32)
+            }
+            _ => None,
+        };
+        let ch = ch.ok_or_else(|| format!("Unknown entity: {:?}", whole.as_str()))?;
+        unescaped.push_str(&text[last..whole.start()]);
+        unescaped.push(ch);
+        last = whole.end();
+    }
+    unescaped.push_str(&text[last..]);
+    Ok(unescaped)
+}
+
+/// Returns the manual translation of a segment, with a line for each filled
+/// in manual line.
+fn manual_translation(segment: &Segment) -> Option<String> {
+    if ! segment.is_translated() {
+        return None;
+    }
+    let lines = segment.manual_lines()
+        .filter(|line| ! line.is_empty())
+        .map(|line| line.content())
+        .collect::<Vec<_>>();
+    Some(lines.join("\n"))
+}
+
+/// Writes the source lines of a line mode document and their manual
+/// translations in the given format. The units are identified by the line
+/// numbers of their source lines.
+pub fn export_work_file(work_file: &WorkFile, format: ExchangeFormat, name: &str,
+        source_lang: &str, target_lang: &str, out: &mut Write) -> io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    match format {
+        ExchangeFormat::Tmx => {
+            writeln!(out, r#"<tmx version="1.4">"#)?;
+            writeln!(out, r#"  <header creationtool="kor" creationtoolversion="{}" segtype="sentence" o-tmf="kor" adminlang="en" srclang="{}" datatype="plaintext"/>"#,
+                env!("CARGO_PKG_VERSION"), escape(source_lang))?;
+            writeln!(out, "  <body>")?;
+            for (number, segment) in work_file.numbered_segments() {
+                // A translation memory only holds translated text
+                let translation = match manual_translation(segment) {
+                    Some(translation) => translation,
+                    None => continue,
+                };
+                writeln!(out, r#"    <tu tuid="{}">"#, number)?;
+                writeln!(out, r#"      <tuv xml:lang="{}"><seg>{}</seg></tuv>"#,
+                    escape(source_lang), escape(segment.source.trim()))?;
+                writeln!(out, r#"      <tuv xml:lang="{}"><seg>{}</seg></tuv>"#,
+                    escape(target_lang), escape(&translation))?;
+                writeln!(out, "    </tu>")?;
+            }
+            writeln!(out, "  </body>")?;
+            writeln!(out, "</tmx>")?;
+        }
+        ExchangeFormat::Xliff => {
+            writeln!(out, r#"<xliff version="1.2" xmlns="urn:oasis:names:tc:xliff:document:1.2">"#)?;
+            writeln!(out, r#"  <file original="{}" source-language="{}" target-language="{}" datatype="plaintext">"#,
+                escape(name), escape(source_lang), escape(target_lang))?;
+            writeln!(out, "    <body>")?;
+            for (number, segment) in work_file.numbered_segments() {
+                writeln!(out, r#"      <trans-unit id="{}">"#, number)?;
+                writeln!(out, "        <source>{}</source>", escape(segment.source.trim()))?;
+                if let Some(translation) = manual_translation(segment) {
+                    writeln!(out, r#"        <target state="translated">{}</target>"#,
+                        escape(&translation))?;
+                }
+                writeln!(out, "      </trans-unit>")?;
+            }
+            writeln!(out, "    </body>")?;
+            writeln!(out, "  </file>")?;
+            writeln!(out, "</xliff>")?;
+        }
+    }
+    out.flush()
+}
+
+/// Returns the text of an XLIFF element, without inline tags.
+fn element_text(content: &str) -> Result<String, String> {
+    unescape(&RE_TAG.replace_all(content, ""))
+}
+
+/// Fills in the manual lines of a line mode document with the targets of an
+/// XLIFF document exported from it. Units without a target are skipped.
+/// Returns the number of source lines that were given a translation.
+pub fn import_xliff(work_file: &mut WorkFile, xliff: &str, path: &str, markers: &Markers)
+        -> Result<usize, KorError> {
+    let parse_error = |pos: usize, message: String| KorError::Parse {
+        kind: FileKind::Document, path: path.to_string(),
+        line: xliff[..pos].matches('\n').count() + 1, message,
+    };
+
+    // Find the targets by the line numbers of their source lines
+    let mut targets = HashMap::new();
+    for caps in RE_TRANS_UNIT.captures_iter(xliff) {
+        let pos = caps.get(0).unwrap().start();
+        let attributes = caps.get(1).unwrap().as_str();
+        let content = caps.get(2).unwrap().as_str();
+        let number = RE_ID.captures(attributes)
+            .and_then(|id| id.get(1).unwrap().as_str().parse::<usize>().ok())
+            .ok_or_else(|| parse_error(pos, format!("Invalid unit id: {:?}", attributes)))?;
+        let target = match RE_TARGET.captures(content) {
+            Some(target) => element_text(target.get(1).unwrap().as_str())
+                .map_err(|message| parse_error(pos, message))?,
+            None => continue,
+        };
+        if target.trim().is_empty() {
+            continue;
+        }
+        let source = match RE_SOURCE.captures(content) {
+            Some(source) => element_text(source.get(1).unwrap().as_str())
+                .map_err(|message| parse_error(pos, message))?,
+            None => return Err(parse_error(pos, format!("Unit {} has no source", number))),
+        };
+        targets.insert(number, (pos, source, target));
+    }
+
+    let numbers = work_file.numbered_segments().map(|(number, _)| number).collect::<Vec<_>>();
+    let mut imported = 0;
+    for (number, segment) in numbers.into_iter().zip(work_file.segments_mut()) {
+        let (pos, source, target) = match targets.remove(&number) {
+            Some(unit) => unit,
+            None => continue,
+        };
+        if source.trim() != segment.source.trim() {
+            return Err(parse_error(pos, format!(
+                "The source of unit {} doesn't match line {} of the document", number, number)));
+        }
+        let lines = target.lines().map(str::trim).filter(|line| ! line.is_empty())
+            .collect::<Vec<_>>();
+        segment.set_manual_translation(&lines, markers);
+        segment.mark_reviewed();
+        imported += 1;
+    }
+
+    if let Some((_, &(pos, _, _))) = targets.iter().min_by_key(|&(_, &(pos, _, _))| pos) {
+        return Err(parse_error(pos, "The unit has no source line in the document".to_string()));
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSLATED: &str = "가다\n-> [to go]\n-| to go\n\n오다 & 보다\n-> [to come]\n-| \n";
+    const UNTRANSLATED: &str = "가다\n-> [to go]\n-| \n\n오다 & 보다\n-> [to come]\n-| \n";
+
+    #[test]
+    fn exported_translations_are_imported() {
+        let markers = Markers::default();
+        let work_file = WorkFile::parse(TRANSLATED, &markers);
+        let mut xliff = Vec::new();
+        export_work_file(&work_file, ExchangeFormat::Xliff, "doc.txt", "ko", "en", &mut xliff)
+            .unwrap();
+        let xliff = String::from_utf8(xliff).unwrap();
+        assert!(xliff.contains(r#"<trans-unit id="5">"#));
+        assert!(xliff.contains("<source>오다 &amp; 보다</source>"));
+
+        let mut work_file = WorkFile::parse(UNTRANSLATED, &markers);
+        assert_eq!(import_xliff(&mut work_file, &xliff, "doc.xlf", &markers).unwrap(), 1);
+        let mut written = Vec::new();
+        work_file.write(&mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), TRANSLATED);
+    }
+
+    #[test]
+    fn unit_ids_are_not_xml_ids() {
+        let caps = RE_ID.captures(r#" xml:id="a2" id="1""#).unwrap();
+        assert_eq!(caps.get(1).unwrap().as_str(), "1");
+        assert!(RE_ID.captures(r#" xml:id="1""#).is_none());
+    }
+
+    #[test]
+    fn entities_are_unescaped() {
+        assert_eq!(unescape("&#54620;&#xAD6D;어 &amp;&lt;&gt;&quot;&apos;").unwrap(),
+            "한국어 &<>\"'");
+        assert!(unescape("&nbsp;").is_err());
+        assert!(unescape("&#xD800;").is_err());
+    }
+
+    #[test]
+    fn sources_must_match() {
+        let markers = Markers::default();
+        let mut work_file = WorkFile::parse(UNTRANSLATED, &markers);
+        let xliff = "<xliff>\n<trans-unit id=\"1\">\n  <source>오다</source>\n  \
+            <target>to come</target>\n</trans-unit>\n</xliff>\n";
+        match import_xliff(&mut work_file, xliff, "doc.xlf", &markers) {
+            Err(KorError::Parse { line, ref message, .. }) => {
+                assert_eq!(line, 2);
+                assert!(message.contains("doesn't match line 1"), "{}", message);
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(! work_file.segments().any(|segment| segment.is_translated()));
+    }
+}
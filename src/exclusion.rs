@@ -0,0 +1,268 @@
+//! Module for the rules that exclude words from the automatic translation.
+//!
+//! Each rule has a pattern, optionally followed by the contexts it applies
+//! in. Without a context, the matching words are removed from the
+//! dictionary.
+//!
+//! Patterns:
+//!   '가다'         The word, and all the forms of a headword written like it.
+//!   '@가다'        Only a headword (or alias), with all the forms generated
+//!                  for it.
+//!   'glob:가*'     Words matching a glob pattern ('*' and '?').
+//!   're:^가.$'     Words matching a regular expression.
+//!
+//! Contexts:
+//!   '>다'          Only when the word is followed by '다'.
+//!   '<그'          Only when the word follows '그'.
+//!
+//! Ex: '이 >다' excludes '이' when it is followed by '다'.
+//! Only the trailing words starting with '>' or '<' are contexts, so a
+//! pattern may have spaces, like '안 되다 >고'.
+
+use std::fmt;
+use regex::{self, Regex};
+use std_unicode::str::UnicodeStr;
+use common::*;
+use error::{KorError, FileKind};
+use normalize::nfc;
+use wordlist::Def;
+
+/// The prefix of document lines with an exclusion rule, after the comment
+/// prefix.
+/// Ex: '-# exclude: @가다'.
+pub const EXCLUDE_DIRECTIVE: &str = "exclude:";
+
+/// What an exclusion rule matches.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// A dictionary key, or a headword with all its keys.
+    Key(String),
+    /// The keys of a definition, by its headword or one of its aliases.
+    Headword(String),
+    /// The keys matching a glob or regular expression.
+    Regex(Regex),
+}
+
+/// A rule that excludes words from the automatic translation.
+#[derive(Debug, Clone)]
+pub struct ExclusionRule {
+    pub pattern: Pattern,
+    /// Text that must come right before the word.
+    pub before: Option<String>,
+    /// Text that must come right after the word.
+    pub after: Option<String>,
+}
+
+/// Returns whether the word is the headword or one of the aliases of the
+/// definition.
+pub fn is_headword(def: &Def, word: &str) -> bool {
+    def.hangeul == word || def.aliases.iter().any(|alias| alias == word)
+}
+
+/// Turns a glob pattern into a regular expression matching whole words.
+fn glob_to_regex(glob: &str) -> String {
+    let mut re = String::from("^");
+    for ch in glob.chars() {
+        match ch {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            _ => re.push_str(&regex::escape(&ch.to_string())),
+        }
+    }
+    re.push('$');
+    re
+}
+
+impl ExclusionRule {
+    /// Reads a rule.
+    pub fn parse(rule: &str) -> Result<ExclusionRule, String> {
+        // Contexts are the trailing words starting with '>' or '<', and the
+        // rest of the rule is the pattern, which may have spaces.
+        let mut pattern = rule.trim();
+        let mut before = None;
+        let mut after = None;
+        while let Some(space) = pattern.rfind(char::is_whitespace) {
+            let context = pattern[space..].trim_left();
+            let (found, text) = if context.starts_with(">") {
+                (&mut after, &context[1..])
+            } else if context.starts_with("<") {
+                (&mut before, &context[1..])
+            } else {
+                break;
+            };
+            if text.is_empty() {
+                return Err(format!("Invalid context: {:?} (expected '>text' or '<text')",
+                    context));
+            }
+            // The rule is read backwards, so the last context of a kind wins
+            if found.is_none() {
+                *found = Some(nfc(text).into_owned());
+            }
+            pattern = pattern[..space].trim_right();
+        }
+
+        if pattern.is_empty() {
+            return Err("Empty exclusion rule".to_string());
+        }
+        let pattern = if pattern.starts_with("@") {
+            Pattern::Headword(nfc(&pattern[1..]).into_owned())
+        } else if pattern.starts_with("glob:") {
+            let re = glob_to_regex(&nfc(&pattern["glob:".len()..]));
+            Pattern::Regex(Regex::new(&re).map_err(|e| format!("Invalid glob: {}", e))?)
+        } else if pattern.starts_with("re:") {
+            let re = nfc(&pattern["re:".len()..]).into_owned();
+            Pattern::Regex(Regex::new(&re).map_err(|e| format!("Invalid regex: {}", e))?)
+        } else {
+            Pattern::Key(nfc(pattern).into_owned())
+        };
+        Ok(ExclusionRule { pattern, before, after })
+    }
+
+    /// Returns whether the rule applies in every context.
+    pub fn is_context_free(&self) -> bool {
+        self.before.is_none() && self.after.is_none()
+    }
+
+    /// Returns whether the rule matches a dictionary key and its definition.
+    pub fn matches_key(&self, key: &str, def: &Def) -> bool {
+        match self.pattern {
+            Pattern::Key(ref word) => key == word || is_headword(def, word),
+            Pattern::Headword(ref word) => is_headword(def, word),
+            Pattern::Regex(ref re) => re.is_match(key),
+        }
+    }
+
+    /// Returns whether the rule matches a word found between the given
+    /// texts.
+    pub fn matches(&self, key: &str, def: &Def, text_before: &str, text_after: &str) -> bool {
+        self.before.as_ref().map(|b| text_before.ends_with(b.as_str())).unwrap_or(true)
+            && self.after.as_ref().map(|a| text_after.starts_with(a.as_str())).unwrap_or(true)
+            && self.matches_key(key, def)
+    }
+}
+
+impl fmt::Display for ExclusionRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.pattern {
+            Pattern::Key(ref word) => write!(f, "{}", word)?,
+            Pattern::Headword(ref word) => write!(f, "@{}", word)?,
+            Pattern::Regex(ref re) => write!(f, "re:{}", re.as_str())?,
+        }
+        if let Some(ref before) = self.before {
+            write!(f, " <{}", before)?;
+        }
+        if let Some(ref after) = self.after {
+            write!(f, " >{}", after)?;
+        }
+        Ok(())
+    }
+}
+
+/// Exclusion rules applied on top of those of a dictionary, like the
+/// directives of a document, so that the dictionary itself is left unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct ExclusionOverlay {
+    rules: Vec<ExclusionRule>,
+}
+
+impl ExclusionOverlay {
+    /// Creates an overlay without rules.
+    pub fn new() -> ExclusionOverlay {
+        ExclusionOverlay::default()
+    }
+
+    /// Adds a rule.
+    pub fn add(&mut self, rule: ExclusionRule) {
+        self.rules.push(rule);
+    }
+
+    /// Returns whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns whether any of the rules match a word found between the given
+    /// texts.
+    pub fn excludes(&self, key: &str, def: &Def, text_before: &str, text_after: &str) -> bool {
+        self.rules.iter().any(|rule| rule.matches(key, def, text_before, text_after))
+    }
+
+    /// Adds the rule of a document line, if the line is a directive.
+    /// Invalid directives are reported and skipped.
+    /// Returns whether the line was a directive.
+    pub fn apply_directive(&mut self, line: &str, comment_prefix: &str) -> bool {
+        match parse_directive(line.trim_right(), comment_prefix) {
+            Some(Ok(rule)) => self.add(rule),
+            Some(Err(message)) => {
+                warn!("Invalid exclusion directive {:?}: {}", line.trim_right(), message);
+            }
+            None => return false,
+        }
+        true
+    }
+}
+
+/// Reads the rules of an exclusion list, with one rule per line.
+/// Lines starting with '#' are ignored.
+pub fn read_exclusions(text: &str, path: &str) -> Result<Vec<ExclusionRule>, KorError> {
+    let mut rules = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.starts_with("#") || line.is_whitespace() {
+            continue;
+        }
+        let rule = ExclusionRule::parse(line).map_err(|message| KorError::Parse {
+            kind: FileKind::ExclusionList, path: path.to_string(), line: i+1, message,
+        })?;
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+/// Returns the exclusion rule of a document line, if it is a directive.
+/// Ex: '-# exclude: 이 >다'.
+pub fn parse_directive(line: &str, comment_prefix: &str) -> Option<Result<ExclusionRule, String>> {
+    if ! line.starts_with(comment_prefix) {
+        return None;
+    }
+    let rest = line[comment_prefix.len()..].trim_left();
+    if ! rest.starts_with(EXCLUDE_DIRECTIVE) {
+        return None;
+    }
+    Some(ExclusionRule::parse(&rest[EXCLUDE_DIRECTIVE.len()..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_keep_their_spaces() {
+        let rule = ExclusionRule::parse("안 되다").unwrap();
+        match rule.pattern {
+            Pattern::Key(ref key) => assert_eq!(key, "안 되다"),
+            ref other => panic!("{:?}", other),
+        }
+        assert!(rule.is_context_free());
+
+        let rule = ExclusionRule::parse("  안 되다  <못 >고 ").unwrap();
+        match rule.pattern {
+            Pattern::Key(ref key) => assert_eq!(key, "안 되다"),
+            ref other => panic!("{:?}", other),
+        }
+        assert_eq!(rule.before, Some("못".to_string()));
+        assert_eq!(rule.after, Some("고".to_string()));
+        assert_eq!(rule.to_string(), "안 되다 <못 >고");
+    }
+
+    #[test]
+    fn contexts_are_checked() {
+        assert!(ExclusionRule::parse("이 >").is_err());
+        assert!(ExclusionRule::parse("   ").is_err());
+        let rule = ExclusionRule::parse("@이다 >다").unwrap();
+        match rule.pattern {
+            Pattern::Headword(ref word) => assert_eq!(word, "이다"),
+            ref other => panic!("{:?}", other),
+        }
+        assert_eq!(rule.after, Some("다".to_string()));
+    }
+}
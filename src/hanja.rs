@@ -0,0 +1,258 @@
+//! Module for working with hanja (Chinese characters used in Korean).
+
+use std::collections::HashMap;
+use std_unicode::str::UnicodeStr;
+use hangeul2::is_hangeul;
+use common::*;
+use dict::Dict;
+use normalize::nfc;
+use wordlist::{Def, Hanja};
+
+/// Returns whether the given character is a hanja (a CJK ideograph).
+pub fn is_hanja(ch: char) -> bool {
+    match ch as u32 {
+        0x3400 ... 0x4DBF
+        | 0x4E00 ... 0x9FFF
+        | 0xF900 ... 0xFAFF
+        | 0x20000 ... 0x2A6DF => true,
+        _ => false,
+    }
+}
+
+/// Pairs each hanja character of the spelling with the hangeul syllable
+/// it is read as.
+/// Hangeul attached after the hanja (like '幼稚-' for '유치하다') is skipped
+/// at the end of the word, and hangeul attached before it (like '-的' for
+/// '-적') at the start.
+pub fn align_readings(hangeul: &str, hanja: &Hanja) -> Vec<(char, char)> {
+    let syllables = hangeul.chars().filter(|&ch| is_hangeul(ch)).collect::<Vec<_>>();
+    let chars = hanja.text.chars().filter(|&ch| is_hanja(ch)).collect::<Vec<_>>();
+    let offset = if syllables.len() == chars.len() {
+        0
+    } else if syllables.len() < chars.len() {
+        return Vec::new();
+    } else if hanja.attached_after && ! hanja.attached_before {
+        0
+    } else if hanja.attached_before && ! hanja.attached_after {
+        syllables.len() - chars.len()
+    } else {
+        return Vec::new();
+    };
+    syllables[offset..].iter().cloned().zip(chars.into_iter()).collect()
+}
+
+/// Returns the hangeul reading of a text, using the readings of the hanja in
+/// the given definition. Characters without a known reading are kept.
+pub fn reading_of(text: &str, def: &Def) -> String {
+    let mut readings = HashMap::new();
+    for spelling in &def.hanja {
+        for (syllable, ch) in align_readings(&def.hangeul, spelling) {
+            readings.entry(ch).or_insert(syllable);
+        }
+    }
+    text.chars().map(|ch| *readings.get(&ch).unwrap_or(&ch)).collect()
+}
+
+/// An entry in a hanja table.
+#[derive(Debug, Clone)]
+pub struct HanjaEntry {
+    pub hanja: char,
+    pub reading: Option<char>,
+    pub meanings: Vec<String>,
+}
+
+/// A table of hanja characters with their readings and meanings.
+#[derive(Debug, Clone, Default)]
+pub struct HanjaTable {
+    entries: HashMap<char, HanjaEntry>,
+    readings: HashMap<char, Vec<char>>,
+}
+
+impl HanjaTable {
+    /// Creates a new, empty table.
+    pub fn new() -> HanjaTable {
+        HanjaTable::default()
+    }
+
+    /// Reads the entries of a hanja table file and adds them to the table.
+    /// Each line has the format '<hanja> [<reading>] <meaning>[, <meaning>...]'
+    /// Ex: '幼 유 young, childish'.
+    /// Newer entries for a character replace older ones.
+    pub fn add_entries(&mut self, text: &str) {
+        for (i, line) in text.lines().enumerate() {
+            if line.starts_with("#") || line.is_whitespace() {
+                continue;
+            }
+            let line = nfc(line);
+            let mut chars = line.trim().chars();
+            let hanja = chars.next().unwrap();
+            if ! is_hanja(hanja) {
+                warn!("Line {}: Invalid hanja entry: {:?}", i+1, line);
+                continue;
+            }
+            let rem = chars.as_str().trim();
+            let (reading, rem) = match rem.chars().next() {
+                Some(ch) if is_hangeul(ch) => (Some(ch), (&rem[ch.len_utf8()..]).trim()),
+                _ => (None, rem),
+            };
+            let meanings = rem.split(|ch| ch == ',' || ch == ';')
+                .map(|s| s.trim())
+                .filter(|s| ! s.is_empty())
+                .map(|s| s.to_string())
+                .collect();
+            if let Some(reading) = reading {
+                let hanja_list = self.readings.entry(reading).or_insert_with(Vec::new);
+                if ! hanja_list.contains(&hanja) {
+                    hanja_list.push(hanja);
+                }
+            }
+            self.entries.insert(hanja, HanjaEntry { hanja, reading, meanings });
+        }
+    }
+
+    /// Returns the entry for the given hanja, if any.
+    pub fn get(&self, hanja: char) -> Option<&HanjaEntry> {
+        self.entries.get(&hanja)
+    }
+
+    /// Returns the hanja in the table that are read as the given syllable.
+    pub fn with_reading(&self, syllable: char) -> &[char] {
+        self.readings.get(&syllable).map(|v| &v[..]).unwrap_or(&[])
+    }
+}
+
+/// Attempts to guess the hanja of a Sino-Korean word, using the hanja
+/// readings found in the dictionary, or the table if a syllable only has
+/// a single candidate there.
+pub fn guess_hanja(word: &str, dict: &Dict, table: &HanjaTable) -> Option<String> {
+    let mut hanja = String::new();
+    for syllable in word.chars() {
+        if let Some(&ch) = dict.hanja_for_reading(syllable).first() {
+            hanja.push(ch);
+        } else if table.with_reading(syllable).len() == 1 {
+            hanja.push(table.with_reading(syllable)[0]);
+        } else {
+            return None;
+        }
+    }
+    Some(hanja)
+}
+
+/// Writes an annotation of the meanings of each character of the hanja.
+/// Ex: '幼稚園: young/childish/garden'.
+pub fn push_annotation(hanja: &str, table: &HanjaTable, text: &mut String) {
+    text.push_str(hanja);
+    text.push_str(": ");
+    for (i, ch) in hanja.chars().enumerate() {
+        if i != 0 {
+            text.push('/');
+        }
+        match table.get(ch).and_then(|e| e.meanings.first()) {
+            Some(meaning) => text.push_str(meaning),
+            None => text.push('?'),
+        }
+    }
+}
+
+/// Adds an untranslated text to the output, annotating the hangeul words
+/// that look like Sino-Korean compounds with the meanings of their hanja.
+/// The longest start of each word with a hanja guess is annotated.
+pub fn annotate_untranslated(text: &str, dict: &Dict, table: &HanjaTable,
+        translated: &mut String) {
+    let mut rem = text;
+    while let Some(start) = rem.find(|ch| is_hangeul(ch)) {
+        translated.push_str(&rem[..start]);
+        rem = &rem[start..];
+        let end = rem.find(|ch| ! is_hangeul(ch)).unwrap_or(rem.len());
+        let word = &rem[..end];
+        let ends = word.char_indices()
+            .map(|(i, ch)| i + ch.len_utf8())
+            .collect::<Vec<_>>();
+        let guess = ends.iter().enumerate().rev()
+            .take_while(|&(n, _)| n >= 1)
+            .filter_map(|(_, &len)| {
+                guess_hanja(&word[..len], dict, table).map(|hanja| (len, hanja))
+            })
+            .next();
+        if let Some((len, hanja)) = guess {
+            translated.push_str(&word[..len]);
+            translated.push('(');
+            push_annotation(&hanja, table, translated);
+            translated.push(')');
+            translated.push_str(&word[len..]);
+        } else {
+            translated.push_str(word);
+        }
+        rem = &rem[end..];
+    }
+    translated.push_str(rem);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use wordlist::read_definitions;
+
+    const TABLE: &str = "\
+# Hanja used in the tests
+幼 유 young, childish
+稚 치 childish
+園 원 garden; park
+";
+
+    fn dict() -> Dict<'static> {
+        let mut dict = Dict::new();
+        dict.add_word_list("유치하다 (幼稚-)\n  to be childish\n-적 (-的)\n  -ic\n");
+        dict
+    }
+
+    #[test]
+    fn readings_are_aligned() {
+        let defs = read_definitions("유치하다 (幼稚-)\n  to be childish\n-적 (-的)\n  -ic\n");
+        assert_eq!(align_readings(&defs[0].hangeul, &defs[0].hanja[0]),
+            vec![('유', '幼'), ('치', '稚')]);
+        assert_eq!(align_readings(&defs[1].hangeul, &defs[1].hanja[0]), vec![('적', '的')]);
+
+        let attached_before = Hanja { text: Cow::Borrowed("的"), attached_before: true,
+            attached_after: false };
+        assert_eq!(align_readings("과학적", &attached_before), vec![('적', '的')]);
+        let attached_both = Hanja { text: Cow::Borrowed("的"), attached_before: true,
+            attached_after: true };
+        assert!(align_readings("과학적", &attached_both).is_empty());
+        // More hanja than syllables can't be aligned
+        assert!(align_readings("유치", &Hanja { text: Cow::Borrowed("幼稚園"),
+            attached_before: false, attached_after: false }).is_empty());
+
+        assert_eq!(reading_of("幼稚園", &defs[0]), "유치園");
+    }
+
+    #[test]
+    fn hanja_are_guessed() {
+        let dict = dict();
+        let mut table = HanjaTable::new();
+        table.add_entries(TABLE);
+        assert_eq!(table.get('園').unwrap().meanings, vec!["garden", "park"]);
+        assert_eq!(guess_hanja("유치원", &dict, &table), Some("幼稚園".to_string()));
+        // Unknown syllables have no guess
+        assert_eq!(guess_hanja("학교", &dict, &table), None);
+        // Neither do syllables with several hanja in the table
+        table.add_entries("院 원 institution\n");
+        assert_eq!(guess_hanja("유치원", &dict, &table), None);
+        assert_eq!(guess_hanja("유치", &dict, &table), Some("幼稚".to_string()));
+    }
+
+    #[test]
+    fn untranslated_words_are_annotated() {
+        let dict = dict();
+        let mut table = HanjaTable::new();
+        table.add_entries(TABLE);
+        let mut translated = String::new();
+        annotate_untranslated("그 유치원에 갔다.", &dict, &table, &mut translated);
+        assert_eq!(translated, "그 유치원(幼稚園: young/childish/garden)에 갔다.");
+
+        let mut translated = String::new();
+        annotate_untranslated("학교", &dict, &table, &mut translated);
+        assert_eq!(translated, "학교");
+    }
+}
@@ -9,11 +9,43 @@ extern crate hangeul2;
 
 #[macro_use]
 mod common;
+mod error;
 mod wordlist;
 mod trie;
+mod normalize;
 mod dict;
+mod hanja;
+mod romanize;
 mod translate;
+mod document;
+mod config;
+mod workfile;
+mod diff;
+mod exchange;
+mod memory;
+mod exclusion;
+mod unknown;
+mod coverage;
 
-pub use wordlist::{Def, read_definitions, read_definitions_iter};
-pub use dict::Dict;
-pub use translate::{translate, translate_iter};
+pub use error::{KorError, FileKind, read_file};
+pub use wordlist::{Def, Hanja, WordList, WordListItem, DefComment, read_definitions, 
+    read_definitions_iter};
+pub use dict::{Dict, KeyOrigin};
+pub use normalize::{nfc, Normalized};
+pub use hanja::{HanjaTable, HanjaEntry, is_hanja, guess_hanja};
+pub use romanize::{Romanization, romanize, romanize_revised, romanize_yale};
+pub use translate::{translate, translate_iter, translate_iter_excluding, translate_with_options, 
+    translate_writer, translate_reader, TranslateOptions, TranslationPart};
+pub use document::{TranslationMode, AUTO_PREFIX, MANUAL_PREFIX, COMMENT_PREFIX, MANUAL_LINES, 
+    translate_document, explain_document, clean_document, CleanLayout, CleanOptions, 
+    untranslated_lines, WorkFileStatus, work_file_status};
+pub use workfile::{WorkFile, Segment, Item, Line, LineKind, UNREVIEWED_MATCH};
+pub use diff::unified_diff;
+pub use exclusion::{ExclusionRule, ExclusionOverlay, Pattern, EXCLUDE_DIRECTIVE, 
+    read_exclusions, parse_directive};
+pub use unknown::{UnknownWord, find_unknown_words, strip_particle, write_scaffold};
+pub use coverage::{Coverage, DocumentCoverage, UNKNOWN_SOURCE, line_coverage, 
+    document_coverage};
+pub use memory::{TranslationMemory, MemoryEntry, MemoryMatch, MIN_MATCH_SCORE};
+pub use exchange::{ExchangeFormat, export_work_file, import_xliff};
+pub use config::{Markers, CONFIG_FILE};
@@ -1,155 +1,653 @@
-#![feature(unicode)]
-
 extern crate argonaut;
 extern crate kor;
-extern crate std_unicode;
 
-use std_unicode::str::UnicodeStr;
 use std::io::prelude::*;
-use std::io;
-use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::fs::{self, File, OpenOptions};
 use std::env;
 use argonaut::{ArgDef, parse, ParseError, help_arg, version_arg};
 use std::process;
-use std::error::Error;
-use kor::{Dict, translate, read_definitions_iter};
+use std::path::Path;
+use kor::{Def, Dict, HanjaTable, TranslateOptions, TranslationMode, Romanization, KorError,
+    FileKind, Markers, CONFIG_FILE, WorkFile, TranslationMemory, UnknownWord, CleanOptions, 
+    CleanLayout, ExchangeFormat, translate_document, explain_document, clean_document, 
+    untranslated_lines, work_file_status, export_work_file, import_xliff, read_definitions, 
+    read_file, is_hanja, romanize, unified_diff, nfc, translate_iter, TranslationPart, 
+    find_unknown_words, write_scaffold, document_coverage};
 
 //const SAMPLE: &str = include_str!("../resources/ch1_sample.txt");
 //const WORD_LIST: &str = include_str!("../resources/ark.wl.txt");
 
-macro_rules! open_and_read_to_string {
-    ($path:expr, $string:expr) => {{
-        let mut file = match File::open($path) {
-            Ok(f) => f,
-            Err(e) => {
-                let _ = write!(io::stderr(), "Could not open file {:?}: {:?}", $path, e.description());
-                return Some(2);
+/// Reads the given files.
+fn read_files(paths: &Vec<String>, kind: FileKind) -> Result<Vec<String>, KorError> {
+    let mut sources = Vec::new();
+    for path in paths {
+        sources.push(read_file(path, kind)?);
+    }
+    Ok(sources)
+}
+
+/// Creates a dictionary from the given word lists.
+/// Definitions without meanings, like unfilled scaffold entries, are skipped.
+fn load_dict<'a>(paths: &Vec<String>, sources: &'a Vec<String>) -> Result<Dict<'a>, KorError> {
+    let mut dict = Dict::new();
+    for (path, source) in paths.iter().zip(sources) {
+        let mut defs = read_definitions(source);
+        defs.retain(|def| {
+            if def.meanings.is_empty() {
+                let _ = writeln!(io::stderr(), "WARN: {}:{}: {:?} has no meanings, skipping it",
+                    path, def.line, def.hangeul);
+                return false;
             }
+            true
+        });
+        dict.add_definitions_from(defs, path);
+    }
+    Ok(dict)
+}
+
+/// Adds the rules of the given exclusion lists to the dictionary.
+fn add_exclusions(dict: &mut Dict, paths: &Vec<String>) -> Result<(), KorError> {
+    for (path, source) in paths.iter().zip(read_files(paths, FileKind::ExclusionList)?) {
+        dict.add_exclusions(&source, path)?;
+    }
+    Ok(())
+}
+
+/// Reads the given hanja table files.
+fn load_hanja_table(paths: &Vec<String>) -> Result<HanjaTable, KorError> {
+    let mut table = HanjaTable::new();
+    for source in read_files(paths, FileKind::HanjaTable)? {
+        table.add_entries(&source);
+    }
+    Ok(table)
+}
+
+/// Reads a translation memory file, or creates an empty memory if it doesn't
+/// exist yet and 'may_be_new' is set.
+fn load_memory(path: &str, may_be_new: bool) -> Result<TranslationMemory, KorError> {
+    if may_be_new && ! Path::new(path).exists() {
+        return Ok(TranslationMemory::new());
+    }
+    let text = read_file(path, FileKind::Memory)?;
+    TranslationMemory::parse(&text, path)
+}
+
+/// Command line settings for the markers of line mode documents.
+#[derive(Debug, Default)]
+struct MarkerArgs {
+    config: Option<String>,
+    auto_prefix: Option<String>,
+    manual_prefix: Option<String>,
+    comment_prefix: Option<String>,
+    manual_lines: Option<String>,
+}
+
+impl MarkerArgs {
+    /// Loads the markers from the configuration file, if any, and then 
+    /// applies the settings from the command line.
+    fn load(&self) -> Result<Markers, KorError> {
+        let mut markers = Markers::default();
+        let config = match self.config {
+            Some(ref path) => Some(path.as_str()),
+            None if Path::new(CONFIG_FILE).is_file() => Some(CONFIG_FILE),
+            None => None,
         };
-        if let Err(e) = file.read_to_string(&mut $string) {
-            let _ = write!(io::stderr(), "Could not read file {:?}: {:?}", $path, e.description());
-            return Some(3);
+        if let Some(path) = config {
+            let text = read_file(path, FileKind::Config)?;
+            markers.read_config(&text, path)?;
+        }
+        if let Some(ref prefix) = self.auto_prefix {
+            markers.auto_prefix = prefix.clone();
+        }
+        if let Some(ref prefix) = self.manual_prefix {
+            markers.manual_prefix = prefix.clone();
+        }
+        if let Some(ref prefix) = self.comment_prefix {
+            markers.comment_prefix = prefix.clone();
+        }
+        if let Some(ref lines) = self.manual_lines {
+            markers.manual_lines = lines.parse().map_err(|_| {
+                KorError::Args(format!("Invalid number of manual lines: {:?}", lines))
+            })?;
+        }
+        markers.validate().map_err(KorError::Args)?;
+        Ok(markers)
+    }
+}
+
+/// Adds the options for the markers of line mode documents to the options of
+/// a subcommand.
+fn with_marker_args<'a>(markers: &'a mut MarkerArgs, mut defs: Vec<ArgDef<'a, 'a>>) 
+        -> Vec<ArgDef<'a, 'a>> {
+    defs.extend(vec![
+          ArgDef::option("config", &mut markers.config)
+            .short("c")
+            .param("file")
+            .help("
+                A configuration file for the line mode markers (defaults to
+                'kor.conf' if it exists).
+            ")
+        
+        , ArgDef::option("auto-prefix", &mut markers.auto_prefix)
+            .param("prefix")
+            .help("The prefix of automatically translated lines (default: '->')")
+        
+        , ArgDef::option("manual-prefix", &mut markers.manual_prefix)
+            .param("prefix")
+            .help("The prefix of manually translated lines (default: '-|')")
+        
+        , ArgDef::option("comment-prefix", &mut markers.comment_prefix)
+            .param("prefix")
+            .help("The prefix of comment lines (default: '-#')")
+    ]);
+    defs
+}
+
+/// Fails if the output is the document itself, which would be emptied before
+/// it is read.
+fn check_output_path(document_path: &str, output_path: Option<&String>) 
+        -> Result<(), KorError> {
+    let output_path = match output_path {
+        Some(path) if document_path != "-" => path,
+        _ => return Ok(()),
+    };
+    match (fs::canonicalize(document_path), fs::canonicalize(output_path)) {
+        (Ok(ref document), Ok(ref output)) if document == output => {
+            Err(KorError::Args(format!(
+                "The output {:?} is the document itself; use --in-place to replace it", 
+                output_path)))
         }
-    };}
+        _ => Ok(()),
+    }
 }
 
-#[derive(Debug)]
-pub enum TranslationMode {
-    Normal,
-    LineByLineWithSpace,
-    Retranslate,
+/// Opens a document for reading, or reads from stdin if the path is '-'.
+fn open_document<'a>(path: &str, stdin: &'a io::Stdin) -> Result<Box<BufRead + 'a>, KorError> {
+    if path == "-" {
+        return Ok(Box::new(stdin.lock()));
+    }
+    match File::open(path) {
+        Ok(file) => Ok(Box::new(BufReader::new(file))),
+        Err(e) => Err(KorError::io(FileKind::Document, path, e)),
+    }
 }
 
-pub const AUTO_PREFIX:   &str = r"->";
-pub const MANUAL_PREFIX: &str = r"-|";
+/// An output that remembers whether writing to it failed, so that write 
+/// errors can be told apart from read errors.
+struct Output<'a> {
+    inner: Box<Write + 'a>,
+    path: String,
+    failed: bool,
+}
 
-// NOTE: Should this be in main or in translate?
-// -> I should probably split the loading/doing parts, and make it output
-// to something that isn't stdout :p.
-// Take a 'target' that can be both stdout and a string? or just a string?
-fn cmd_translate(document_path: &str, word_list_files: &Vec<String>, 
-        exclusion_files: &Vec<String>, mode: TranslationMode) 
-        -> Option<i32> {
-    use self::TranslationMode::*;
+impl<'a> Output<'a> {
+    /// Creates the output file, or writes to stdout if no path is given.
+    fn create(path: Option<&String>, stdout: &'a io::Stdout) -> Result<Output<'a>, KorError> {
+        let (inner, path): (Box<Write + 'a>, _) = match path {
+            Some(path) => {
+                let file = File::create(path)
+                    .map_err(|e| KorError::io(FileKind::Output, path, e))?;
+                (Box::new(BufWriter::new(file)), path.clone())
+            }
+            None => (Box::new(stdout.lock()), String::from("<stdout>")),
+        };
+        Ok(Output { inner, path, failed: false })
+    }
     
-    let mut def_sources = Vec::new();
-    for path in word_list_files {
-        let mut source = String::new();
-        open_and_read_to_string!(path, &mut source);
-        def_sources.push(source);
+    /// Returns the error to report for a failure while using this output.
+    fn error(&self, document_path: &str, err: io::Error) -> KorError {
+        if self.failed {
+            KorError::io(FileKind::Output, &self.path, err)
+        } else {
+            KorError::io(FileKind::Document, document_path, err)
+        }
+    }
+}
+
+impl<'a> Write for Output<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let res = self.inner.write(buf);
+        self.failed |= res.is_err();
+        res
     }
     
-    let mut defs = Vec::new();
-    for source in &def_sources {
-        read_definitions_iter(source, |def| {
-            defs.push(def);
-        });
+    fn flush(&mut self) -> io::Result<()> {
+        let res = self.inner.flush();
+        self.failed |= res.is_err();
+        res
+    }
+}
+
+/// Replaces the file with the new text, and keeps the old file, if there is
+/// one, with a '.bak' extension. The text is written to a temporary file
+/// first, so that the file is never left half-written.
+fn write_in_place(path: &str, text: &[u8]) -> Result<(), KorError> {
+    let tmp_path = format!("{}.kor-tmp", path);
+    let backup_path = format!("{}.bak", path);
+    File::create(&tmp_path)
+        .and_then(|mut file| file.write_all(text).and_then(|_| file.sync_all()))
+        .map_err(|e| KorError::io(FileKind::Output, &tmp_path, e))?;
+    if Path::new(path).exists() {
+        fs::copy(path, &backup_path)
+            .map_err(|e| KorError::io(FileKind::Output, &backup_path, e))?;
+    }
+    fs::rename(&tmp_path, path)
+        .map_err(|e| KorError::io(FileKind::Output, path, e))?;
+    Ok(())
+}
+
+/// Prints a definition in the word list format.
+fn print_def(def: &Def) {
+    println!("{}", def);
+}
+
+fn cmd_translate(document_path: &str, output_path: Option<&String>, 
+        word_list_files: &Vec<String>, exclusion_files: &Vec<String>, 
+        hanja_table_files: &Vec<String>, show_hanja_reading: bool, 
+        romanization: Option<Romanization>, mode: TranslationMode, markers: &MarkerArgs,
+        memory_path: Option<&String>, min_match_score: Option<&String>, in_place: bool, 
+        dry_run: bool, explain: bool) -> Result<(), KorError> {
+    if explain && (in_place || dry_run) {
+        return Err(KorError::Args("--explain can't be used with --in-place or --dry-run".into()));
     }
+    if in_place || dry_run {
+        if mode != TranslationMode::Retranslate {
+            return Err(KorError::Args("--in-place and --dry-run require --retranslate".into()));
+        } else if document_path == "-" || output_path.is_some() {
+            return Err(KorError::Args(
+                "--in-place and --dry-run need a document file and no --output".into()));
+        }
+    }
+    check_output_path(document_path, output_path)?;
     
+    let markers = markers.load()?;
+    let def_sources = read_files(word_list_files, FileKind::WordList)?;
+    let mut dict = load_dict(word_list_files, &def_sources)?;
     
-    let mut dict = Dict::new();
-    dict.add_definitions(defs);
-    
-    for path in exclusion_files {
-        let mut source = String::new();
-        open_and_read_to_string!(path, &mut source);
-        for line in source.lines() {
-            if line.starts_with("#") || line.is_whitespace() {
-                continue;
-            } else {
-                dict.remove(line.trim());
-            }
+    add_exclusions(&mut dict, exclusion_files)?;
+    
+    let hanja_table = load_hanja_table(hanja_table_files)?;
+    let memory = match memory_path {
+        Some(path) => Some(load_memory(path, false)?),
+        None => None,
+    };
+    let mut options = TranslateOptions::default();
+    options.show_hanja_reading = show_hanja_reading;
+    options.romanization = romanization;
+    if ! hanja_table_files.is_empty() {
+        options.hanja_table = Some(&hanja_table);
+    }
+    options.memory = memory.as_ref();
+    if let Some(score) = min_match_score {
+        options.min_match_score = match score.parse() {
+            Ok(score) if score <= 100 => score,
+            _ => return Err(KorError::Args(format!("Invalid match score: {:?}", score))),
+        };
+    }
+    
+    if in_place || dry_run {
+        let text = read_file(document_path, FileKind::Document)?;
+        let mut translated = Vec::new();
+        translate_document(&mut text.as_bytes(), &dict, &options, mode, &markers, 
+                &mut translated)
+            .map_err(|e| KorError::io(FileKind::Document, document_path, e))?;
+        if dry_run {
+            let new_text = String::from_utf8_lossy(&translated);
+            let new_name = format!("{} (retranslated)", document_path);
+            print!("{}", unified_diff(&text, &new_text, document_path, &new_name, 3));
+            return Ok(());
         }
+        return write_in_place(document_path, &translated);
+    }
+    
+    let stdin = io::stdin();
+    let mut input = open_document(document_path, &stdin)?;
+    let stdout = io::stdout();
+    let mut out = Output::create(output_path, &stdout)?;
+    
+    if explain {
+        return explain_document(&mut *input, &dict, mode, &markers, &mut out)
+            .map_err(|e| out.error(document_path, e));
     }
+    translate_document(&mut *input, &dict, &options, mode, &markers, &mut out)
+        .map_err(|e| out.error(document_path, e))
+}
+
+fn cmd_hanja(query: &str, word_list_files: &Vec<String>, 
+        hanja_table_files: &Vec<String>) -> Result<(), KorError> {
+    let def_sources = read_files(word_list_files, FileKind::WordList)?;
+    let dict = load_dict(word_list_files, &def_sources)?;
+    let hanja_table = load_hanja_table(hanja_table_files)?;
     
-    let mut text = String::new();
-    open_and_read_to_string!(&document_path, &mut text);
-    
-    match mode {
-        Normal => {
-            let translated = translate(&text, &dict);
-            println!("{}", translated);
-        }
-        LineByLineWithSpace => {
-            for line in text.lines() {
-                if line.is_whitespace() {
-                    println!("{}", line);
-                    continue;
-                }
-                let translated = translate(line, &dict);
-                println!("{}", line);
-                if translated != line {
-                    println!("{} {}", AUTO_PREFIX, translated);
-                }
-                println!("{} ", MANUAL_PREFIX);
-                println!("{} ", MANUAL_PREFIX);
-                println!("{} ", MANUAL_PREFIX);
+    let query = query.trim();
+    if query.chars().count() > 1 {
+        let found = dict.find_by_hanja(query);
+        println!("== {} ({} found)", query, found.len());
+        for def in found {
+            print_def(def);
+        }
+        println!("");
+    }
+    for ch in query.chars().filter(|&ch| is_hanja(ch)) {
+        let found = dict.find_by_hanja_char(ch);
+        let mut header = format!("== {}", ch);
+        if let Some(entry) = hanja_table.get(ch) {
+            if let Some(reading) = entry.reading {
+                header.push_str(&format!(" {}", reading));
+            }
+            if ! entry.meanings.is_empty() {
+                header.push_str(&format!(": {}", entry.meanings.join(", ")));
             }
         }
-        Retranslate => {
-            for line in text.lines() {
-                if line.is_whitespace() {
-                    println!("{}", line);
-                
-                } else if line.starts_with(MANUAL_PREFIX) {
-                    println!("{}", line);
-                
-                } else if line.starts_with(AUTO_PREFIX) {
-                    // Remove the automatically translated lines
-                
-                } else {
-                    let translated = translate(line, &dict);
-                    println!("{}", line);
-                    if translated != line {
-                        println!("{} {}", AUTO_PREFIX, translated);
-                    }
-                }
+        println!("{} ({} found)", header, found.len());
+        for def in found {
+            print_def(def);
+        }
+        println!("");
+    }
+    
+    Ok(())
+}
+
+/// Prints the definitions with a meaning containing the query.
+fn print_reverse_lookup(query: &str, dict: &Dict) {
+    let found = dict.search_meanings(query);
+    println!("== {} ({} found)", query, found.len());
+    for def in found {
+        print_def(def);
+    }
+}
+
+/// Prints how the text is split into words by the dictionary, with the key
+/// each word matched, how the key was made and its full definition.
+fn print_lookup(query: &str, dict: &Dict) {
+    println!("== {}", query);
+    translate_iter(query, dict, |part| match part {
+        TranslationPart::Untranslated(src) => {
+            if ! src.trim().is_empty() {
+                println!("-- {} (not found)", src.trim());
             }
         }
+        TranslationPart::Translated(src, def) => {
+            let key = nfc(src);
+            let origin = dict.key_origin(&key)
+                .map(|origin| origin.to_string())
+                .unwrap_or_else(|| String::from("unknown origin"));
+            println!("-- {} (key {:?}, {})", src, key, origin);
+            print_def(def);
+        }
+    });
+}
+
+fn cmd_lookup(query: &str, word_list_files: &Vec<String>, exclusion_files: &Vec<String>,
+        reverse: bool) -> Result<(), KorError> {
+    let def_sources = read_files(word_list_files, FileKind::WordList)?;
+    let mut dict = load_dict(word_list_files, &def_sources)?;
+    add_exclusions(&mut dict, exclusion_files)?;
+    
+    if reverse {
+        print_reverse_lookup(query.trim(), &dict);
+    } else {
+        print_lookup(query.trim(), &dict);
+    }
+    Ok(())
+}
+
+fn cmd_repl(word_list_files: &Vec<String>, exclusion_files: &Vec<String>) 
+        -> Result<(), KorError> {
+    let def_sources = read_files(word_list_files, FileKind::WordList)?;
+    let mut dict = load_dict(word_list_files, &def_sources)?;
+    add_exclusions(&mut dict, exclusion_files)?;
+    
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        io::stdout().flush()
+            .map_err(|e| KorError::io(FileKind::Output, "<stdout>", e))?;
+        line.clear();
+        let read = input.read_line(&mut line)
+            .map_err(|e| KorError::io(FileKind::Document, "<stdin>", e))?;
+        if read == 0 {
+            println!("");
+            return Ok(());
+        }
+        let query = line.trim();
+        if query.is_empty() {
+            continue;
+        } else if query.starts_with("?") {
+            print_reverse_lookup(query[1..].trim(), &dict);
+        } else {
+            print_lookup(query, &dict);
+        }
+        println!("");
+    }
+}
+
+/// Finds the unknown words of a document, at most 'limit' of them.
+fn load_unknown_words(document_path: &str, word_list_files: &Vec<String>, 
+        exclusion_files: &Vec<String>, markers: &MarkerArgs, limit: Option<&String>) 
+        -> Result<Vec<UnknownWord>, KorError> {
+    let markers = markers.load()?;
+    let limit = match limit.map(|limit| limit.parse::<usize>()) {
+        Some(Ok(limit)) => Some(limit),
+        Some(Err(_)) => return Err(KorError::Args(format!("Invalid limit: {:?}", limit.unwrap()))),
+        None => None,
+    };
+    let def_sources = read_files(word_list_files, FileKind::WordList)?;
+    let mut dict = load_dict(word_list_files, &def_sources)?;
+    add_exclusions(&mut dict, exclusion_files)?;
+    let text = read_file(document_path, FileKind::Document)?;
+    
+    let mut words = find_unknown_words(&text, &dict, &markers);
+    if let Some(limit) = limit {
+        words.truncate(limit);
+    }
+    Ok(words)
+}
+
+fn cmd_unknown(document_path: &str, word_list_files: &Vec<String>, 
+        exclusion_files: &Vec<String>, markers: &MarkerArgs, limit: Option<&String>) 
+        -> Result<(), KorError> {
+    let words = load_unknown_words(document_path, word_list_files, exclusion_files, markers,
+        limit)?;
+    for word in words {
+        println!("{:>6}  {}", word.count, word.word);
+    }
+    
+    Ok(())
+}
+
+fn cmd_scaffold(document_path: &str, word_list_files: &Vec<String>, 
+        exclusion_files: &Vec<String>, markers: &MarkerArgs, limit: Option<&String>, 
+        output_path: Option<&String>, append_path: Option<&String>) -> Result<(), KorError> {
+    let words = load_unknown_words(document_path, word_list_files, exclusion_files, markers,
+        limit)?;
+    
+    if let Some(path) = append_path {
+        if output_path.is_some() {
+            return Err(KorError::Args("--append can't be used with --output".into()));
+        }
+        let mut file = OpenOptions::new().append(true).create(true).open(path)
+            .map_err(|e| KorError::io(FileKind::WordList, path, e))?;
+        return write_scaffold(&words, &mut file)
+            .map_err(|e| KorError::io(FileKind::Output, path, e));
     }
     
-    None
+    let stdout = io::stdout();
+    let mut out = Output::create(output_path, &stdout)?;
+    write_scaffold(&words, &mut out)
+        .map_err(|e| out.error(document_path, e))
 }
 
-fn cmd_clean(document_path: &str) -> Option<i32> {
-    let mut text = String::new();
-    open_and_read_to_string!(&document_path, &mut text);
+fn cmd_coverage(document_path: &str, word_list_files: &Vec<String>, 
+        exclusion_files: &Vec<String>, markers: &MarkerArgs, summary_only: bool) 
+        -> Result<(), KorError> {
+    const BAR_WIDTH: usize = 40;
+    
+    let markers = markers.load()?;
+    let def_sources = read_files(word_list_files, FileKind::WordList)?;
+    let mut dict = load_dict(word_list_files, &def_sources)?;
+    add_exclusions(&mut dict, exclusion_files)?;
+    let text = read_file(document_path, FileKind::Document)?;
+    
+    let coverage = document_coverage(&text, &dict, &markers);
+    if ! summary_only {
+        for &(line, ref covered) in &coverage.lines {
+            println!("Line {:>5}: {:>5.1}% of characters, {:>5.1}% of words ({}/{})", line, 
+                covered.char_percentage(), covered.word_percentage(), covered.covered_words,
+                covered.words);
+        }
+        println!("");
+    }
+    
+    let total = &coverage.total;
+    println!("Characters: {}/{} ({:.1}%)", total.covered_chars, total.chars, 
+        total.char_percentage());
+    println!("Words:      {}/{} ({:.1}%)", total.covered_words, total.words, 
+        total.word_percentage());
+    println!("  Partly translated: {}", total.partly_covered_words);
+    
+    if let Some(&(_, most)) = coverage.sources.first() {
+        println!("");
+        println!("Translated words by word list:");
+        let name_width = coverage.sources.iter().map(|s| s.0.chars().count()).max().unwrap_or(0);
+        for &(ref name, count) in &coverage.sources {
+            let bar = ::std::iter::repeat('#').take(count * BAR_WIDTH / most).collect::<String>();
+            println!("  {:<width$}  {:>6}  {}", name, count, bar, width = name_width);
+        }
+    }
+    
+    Ok(())
+}
+
+fn cmd_romanize(document_path: &str, system: Romanization) -> Result<(), KorError> {
+    let text = read_file(document_path, FileKind::Document)?;
     
     for line in text.lines() {
-        if line.is_whitespace() {
-            println!("{}", line.trim());
-        } else if line.starts_with(MANUAL_PREFIX) {
-            let rem = (&line[MANUAL_PREFIX.len()..]).trim();
-            if rem.is_whitespace() {
-                // Indicate that something wasn't translated
-                println!("{} ", MANUAL_PREFIX);
-            } else {
-                println!("{}", rem);
-            }
+        println!("{}", romanize(line, system));
+    }
+    
+    Ok(())
+}
+
+fn cmd_clean(document_path: &str, output_path: Option<&String>, markers: &MarkerArgs, 
+        options: &CleanOptions, strict: bool, memory_path: Option<&String>) 
+        -> Result<(), KorError> {
+    let markers = markers.load()?;
+    let stdin = io::stdin();
+    let mut input = open_document(document_path, &stdin)?;
+    let work_file = WorkFile::read(&mut *input, &markers)
+        .map_err(|e| KorError::io(FileKind::Document, document_path, e))?;
+    
+    if strict {
+        let lines = untranslated_lines(&work_file, options);
+        if ! lines.is_empty() {
+            return Err(KorError::Untranslated { path: document_path.to_string(), lines });
         }
     }
     
-    None
+    {
+        let stdout = io::stdout();
+        let mut out = Output::create(output_path, &stdout)?;
+        clean_document(&work_file, &markers, options, &mut out)
+            .map_err(|e| out.error(document_path, e))?;
+    }
+    
+    if let Some(memory_path) = memory_path {
+        let mut memory = load_memory(memory_path, true)?;
+        let added = memory.add_work_file(&work_file);
+        let mut text = Vec::new();
+        memory.write(&mut text)
+            .map_err(|e| KorError::io(FileKind::Output, memory_path, e))?;
+        write_in_place(memory_path, &text)?;
+        let _ = writeln!(io::stderr(), "Added {} translation(s) to {:?}", added, memory_path);
+    }
+    Ok(())
+}
+
+fn cmd_export(document_path: &str, output_path: Option<&String>, format: ExchangeFormat,
+        source_lang: &str, target_lang: &str, markers: &MarkerArgs) -> Result<(), KorError> {
+    let markers = markers.load()?;
+    let stdin = io::stdin();
+    let mut input = open_document(document_path, &stdin)?;
+    let work_file = WorkFile::read(&mut *input, &markers)
+        .map_err(|e| KorError::io(FileKind::Document, document_path, e))?;
+    
+    let stdout = io::stdout();
+    let mut out = Output::create(output_path, &stdout)?;
+    export_work_file(&work_file, format, document_path, source_lang, target_lang, &mut out)
+        .map_err(|e| out.error(document_path, e))
+}
+
+fn cmd_import(document_path: &str, xliff_path: &str, output_path: Option<&String>, 
+        markers: &MarkerArgs, in_place: bool) -> Result<(), KorError> {
+    if in_place && (document_path == "-" || output_path.is_some()) {
+        return Err(KorError::Args("--in-place needs a document file and no --output".into()));
+    }
+    check_output_path(document_path, output_path)?;
+    
+    let markers = markers.load()?;
+    let stdin = io::stdin();
+    let mut input = open_document(document_path, &stdin)?;
+    let mut work_file = WorkFile::read(&mut *input, &markers)
+        .map_err(|e| KorError::io(FileKind::Document, document_path, e))?;
+    let xliff = read_file(xliff_path, FileKind::Document)?;
+    
+    let imported = import_xliff(&mut work_file, &xliff, xliff_path, &markers)?;
+    let _ = writeln!(io::stderr(), "Imported translations for {} line(s)", imported);
+    
+    if in_place {
+        let mut text = Vec::new();
+        work_file.write(&mut text)
+            .map_err(|e| KorError::io(FileKind::Output, document_path, e))?;
+        return write_in_place(document_path, &text);
+    }
+    
+    let stdout = io::stdout();
+    let mut out = Output::create(output_path, &stdout)?;
+    work_file.write(&mut out)
+        .and_then(|_| out.flush())
+        .map_err(|e| out.error(document_path, e))
+}
+
+fn cmd_status(document_path: &str, word_list_files: &Vec<String>, markers: &MarkerArgs) 
+        -> Result<(), KorError> {
+    let markers = markers.load()?;
+    let def_sources = read_files(word_list_files, FileKind::WordList)?;
+    let dict = load_dict(word_list_files, &def_sources)?;
+    
+    let stdin = io::stdin();
+    let mut input = open_document(document_path, &stdin)?;
+    let status = work_file_status(&mut *input, &dict, &markers)
+        .map_err(|e| KorError::io(FileKind::Document, document_path, e))?;
+    
+    println!("Source lines:           {}", status.source_lines);
+    println!("  Translated:           {} ({:.1}%)", status.translated_lines, 
+        status.translated_percentage());
+    println!("  Unreviewed matches:   {}", status.unreviewed_lines);
+    println!("  Empty placeholders:   {}", status.placeholder_lines);
+    println!("  Without manual lines: {}", status.missing_lines);
+    if ! word_list_files.is_empty() {
+        println!("Dictionary coverage:    {}/{} words ({:.1}%)", status.covered_words, 
+            status.words, status.coverage_percentage());
+        println!("  Partly translated:    {}", status.partly_covered_words);
+    }
+    
+    Ok(())
+}
+
+/// Reports the error of a command, if any, and returns its exit code.
+fn report(res: Result<(), KorError>) -> Option<i32> {
+    match res {
+        Ok(()) => None,
+        Err(e) => {
+            let _ = writeln!(io::stderr(), "ERROR: {}", e);
+            Some(e.exit_code())
+        }
+    }
 }
 
 fn main() {
@@ -164,6 +662,15 @@ fn argonaut_main() -> Option<i32> {
     
     const DESC: &str = "
         Utility to aid with the translation of Korean text documents.
+        
+        Exit codes:
+          1: Invalid arguments.
+          2: A word list, exclusion list, hanja table, configuration file or
+             translation memory could not be read.
+          3: A document could not be read.
+          4: The output could not be written.
+          5: A file could not be parsed.
+          7: A document has untranslated lines (clean --strict).
     ";
     
     match parse("kor", &args, vec![
@@ -176,13 +683,29 @@ fn argonaut_main() -> Option<i32> {
             
             let mut word_list_files: Vec<String> = Vec::new();
             let mut document_path = String::new();
+            let mut output_path: Option<String> = None;
             let mut exclusion_files: Vec<String> = Vec::new();
+            let mut hanja_table_files: Vec<String> = Vec::new();
+            let mut show_hanja_reading = false;
+            let mut romanization: Option<String> = None;
             let mut use_line_mode = false;
             let mut retranslate_instead = false;
+            let mut in_place = false;
+            let mut dry_run = false;
+            let mut markers = MarkerArgs::default();
+            let mut manual_lines: Option<String> = None;
+            let mut memory_path: Option<String> = None;
+            let mut min_match_score: Option<String> = None;
+            let mut explain = false;
             
-            parse(name, args, vec![
+            parse(name, args, with_marker_args(&mut markers, vec![
                   ArgDef::positional("document", &mut document_path)
-                    .help("A document to translate")
+                    .help("A document to translate, or '-' to read it from stdin")
+                
+                , ArgDef::option("output", &mut output_path)
+                    .short("o")
+                    .param("file")
+                    .help("A file to write the translation to, instead of stdout")
         
                 , ArgDef::collect("word-list", &mut word_list_files)
                     .short("w")
@@ -192,12 +715,70 @@ fn argonaut_main() -> Option<i32> {
                  ArgDef::collect("exclusion-rules", &mut exclusion_files)
                     .short("x")
                     .param("file")
-                    .help("Files with one word per line to exclude from the automatic translation")
+                    .help("
+                        Files with rules for words to exclude from the automatic
+                        translation, one per line: a word (with all its forms, if it
+                        is a headword), '@headword', 'glob:pattern' or 're:regex',
+                        followed by optional contexts like '>next' or '<previous'.
+                        Documents can add rules with '-# exclude: <rule>' lines.
+                    ")
+                
+                , ArgDef::collect("hanja-table", &mut hanja_table_files)
+                    .short("H")
+                    .param("file")
+                    .help("
+                        Hanja tables used to annotate unknown Sino-Korean words with
+                        the meanings of their characters.
+                    ")
+                
+                , ArgDef::flag("hanja-reading", &mut show_hanja_reading)
+                    .help("Show the hangeul reading of translated words written in hanja")
+                
+                , ArgDef::option("romanize", &mut romanization)
+                    .param("system")
+                    .help("Show the romanization ('rr' or 'yale') of translated words")
                 
                 , ArgDef::flag("retranslate", &mut retranslate_instead)
                     .short("r")
                     .help("Retranslates the file, keeping existing user-translated lines")
                 
+                , ArgDef::flag("in-place", &mut in_place)
+                    .short("i")
+                    .help("
+                        Replaces the retranslated file instead of printing it, keeping 
+                        the old version with a '.bak' extension.
+                    ")
+                
+                , ArgDef::flag("dry-run", &mut dry_run)
+                    .short("n")
+                    .help("Shows the changes a retranslation would make as a unified diff")
+                
+                , ArgDef::option("manual-lines", &mut manual_lines)
+                    .param("count")
+                    .help("The number of blank manual lines under each line (default: 3)")
+                
+                , ArgDef::option("memory", &mut memory_path)
+                    .short("m")
+                    .param("file")
+                    .help("
+                        A translation memory used to fill in the manual lines of new
+                        lines with earlier translations of the same or similar text.
+                        Similar text is marked with an 'Unreviewed memory match'
+                        comment, which is to be removed once the lines are checked.
+                    ")
+                
+                , ArgDef::option("min-score", &mut min_match_score)
+                    .param("score")
+                    .help("The minimum score (0-100) of the memory matches used (default: 70)")
+                
+                , ArgDef::flag("explain", &mut explain)
+                    .short("e")
+                    .help("
+                        Instead of translating, show for each translated word the key it
+                        matched, its headword, how the key was made from it (alias,
+                        '하다' stem or conjugation) and the word list line it is from.
+                    ")
+                
                 , ArgDef::flag("use-line-mode", &mut use_line_mode)
                     .short("l")
                     .help("
@@ -206,8 +787,9 @@ fn argonaut_main() -> Option<i32> {
                     ")
                 
                 , help_arg(DESC).short("h")
-            ])?;
+            ]))?;
             
+            markers.manual_lines = manual_lines;
             let mut mode = TranslationMode::Normal;
             if use_line_mode {
                 mode = TranslationMode::LineByLineWithSpace;
@@ -216,40 +798,492 @@ fn argonaut_main() -> Option<i32> {
                 mode = TranslationMode::Retranslate;
             }
             
-            let res = cmd_translate(&document_path, &word_list_files, &exclusion_files, mode);
-            Ok(res)
+            let romanization = match romanization.map(|s| s.parse::<Romanization>()) {
+                Some(Ok(system)) => Some(system),
+                Some(Err(e)) => return Ok(report(Err(KorError::Args(e)))),
+                None => None,
+            };
+            
+            let res = cmd_translate(&document_path, output_path.as_ref(), &word_list_files, 
+                &exclusion_files, &hanja_table_files, show_hanja_reading, romanization, mode,
+                &markers, memory_path.as_ref(), min_match_score.as_ref(), in_place, dry_run,
+                explain);
+            Ok(report(res))
         })
         
-        , ArgDef::subcommand("clean", |name, args| {
+        , ArgDef::subcommand("hanja", |name, args| {
+            const DESC: &str = "
+                Looks up the words written with the given hanja, and the words
+                sharing each of its characters.
+            ";
+            
+            let mut query = String::new();
+            let mut word_list_files: Vec<String> = Vec::new();
+            let mut hanja_table_files: Vec<String> = Vec::new();
+            
+            parse(name, args, vec![
+                  ArgDef::positional("hanja", &mut query)
+                    .help("The hanja word or characters to look up")
+                
+                , ArgDef::collect("word-list", &mut word_list_files)
+                    .short("w")
+                    .param("file")
+                    .help("Word lists to read definitions from.")
+                
+                , ArgDef::collect("hanja-table", &mut hanja_table_files)
+                    .short("H")
+                    .param("file")
+                    .help("Hanja tables to read character readings and meanings from.")
+                
+                , help_arg(DESC).short("h")
+            ])?;
+            
+            let res = cmd_hanja(&query, &word_list_files, &hanja_table_files);
+            Ok(report(res))
+        })
+        
+        , ArgDef::subcommand("lookup", |name, args| {
+            const DESC: &str = "
+                Looks up a Korean word or phrase: how it is split into words, the
+                key each word matched and how the key was made, and the full
+                definitions. With --reverse, finds the Korean words with a meaning
+                containing the given English text.
+            ";
+            
+            let mut query = String::new();
+            let mut word_list_files: Vec<String> = Vec::new();
+            let mut exclusion_files: Vec<String> = Vec::new();
+            let mut reverse = false;
+            
+            parse(name, args, vec![
+                  ArgDef::positional("word", &mut query)
+                    .help("The word to look up")
+                
+                , ArgDef::collect("word-list", &mut word_list_files)
+                    .short("w")
+                    .param("file")
+                    .help("Word lists to read definitions from.")
+                
+                , ArgDef::collect("exclusion-rules", &mut exclusion_files)
+                    .short("x")
+                    .param("file")
+                    .help("Files with rules for words to exclude from the translation")
+                
+                , ArgDef::flag("reverse", &mut reverse)
+                    .short("r")
+                    .help("
+                        Search the meanings for the given text instead, as whole
+                        words and ignoring case.
+                    ")
+                
+                , help_arg(DESC).short("h")
+            ])?;
+            
+            let res = cmd_lookup(&query, &word_list_files, &exclusion_files, reverse);
+            Ok(report(res))
+        })
+        
+        , ArgDef::subcommand("repl", |name, args| {
+            const DESC: &str = "
+                Loads the word lists once, and looks up each line typed in: how it
+                is split into words, the key each word matched, how the key was
+                made (like the conjugation rule) and its full definition. Lines
+                starting with '?' search the meanings instead.
+            ";
+            
+            let mut word_list_files: Vec<String> = Vec::new();
+            let mut exclusion_files: Vec<String> = Vec::new();
+            
+            parse(name, args, vec![
+                  ArgDef::collect("word-list", &mut word_list_files)
+                    .short("w")
+                    .param("file")
+                    .help("Word lists to read definitions from.")
+                
+                , ArgDef::collect("exclusion-rules", &mut exclusion_files)
+                    .short("x")
+                    .param("file")
+                    .help("Files with rules for words to exclude from the translation")
+                
+                , help_arg(DESC).short("h")
+            ])?;
+            
+            let res = cmd_repl(&word_list_files, &exclusion_files);
+            Ok(report(res))
+        })
+        
+        , ArgDef::subcommand("unknown", |name, args| {
+            const DESC: &str = "
+                Lists the words of a document that aren't in the word lists, the
+                most frequent first. Common particles are removed from the words,
+                and untranslated endings of known words are skipped.
+            ";
+            
+            let mut document_path = String::new();
+            let mut word_list_files: Vec<String> = Vec::new();
+            let mut exclusion_files: Vec<String> = Vec::new();
+            let mut markers = MarkerArgs::default();
+            let mut limit: Option<String> = None;
+            
+            parse(name, args, with_marker_args(&mut markers, vec![
+                  ArgDef::positional("document", &mut document_path)
+                    .help("The document to find unknown words in")
+                
+                , ArgDef::collect("word-list", &mut word_list_files)
+                    .short("w")
+                    .param("file")
+                    .help("Word lists to read definitions from.")
+                
+                , ArgDef::collect("exclusion-rules", &mut exclusion_files)
+                    .short("x")
+                    .param("file")
+                    .help("Files with rules for words to exclude from the translation")
+                
+                , ArgDef::option("limit", &mut limit)
+                    .short("n")
+                    .param("count")
+                    .help("Only list the given number of words")
+                
+                , help_arg(DESC).short("h")
+            ]))?;
+            
+            let res = cmd_unknown(&document_path, &word_list_files, &exclusion_files, &markers,
+                limit.as_ref());
+            Ok(report(res))
+        })
+        
+        , ArgDef::subcommand("scaffold", |name, args| {
+            const DESC: &str = "
+                Writes a word list entry for each word of a document that isn't in
+                the word lists, with an empty meaning to fill in and the line it
+                was found in as a comment.
+            ";
+            
+            let mut document_path = String::new();
+            let mut word_list_files: Vec<String> = Vec::new();
+            let mut exclusion_files: Vec<String> = Vec::new();
+            let mut markers = MarkerArgs::default();
+            let mut limit: Option<String> = None;
+            let mut output_path: Option<String> = None;
+            let mut append_path: Option<String> = None;
+            
+            parse(name, args, with_marker_args(&mut markers, vec![
+                  ArgDef::positional("document", &mut document_path)
+                    .help("The document to find unknown words in")
+                
+                , ArgDef::collect("word-list", &mut word_list_files)
+                    .short("w")
+                    .param("file")
+                    .help("Word lists to read definitions from.")
+                
+                , ArgDef::collect("exclusion-rules", &mut exclusion_files)
+                    .short("x")
+                    .param("file")
+                    .help("Files with rules for words to exclude from the translation")
+                
+                , ArgDef::option("limit", &mut limit)
+                    .short("n")
+                    .param("count")
+                    .help("Only write entries for the given number of most frequent words")
+                
+                , ArgDef::option("output", &mut output_path)
+                    .short("o")
+                    .param("file")
+                    .help("A file to write the entries to, instead of stdout")
+                
+                , ArgDef::option("append", &mut append_path)
+                    .short("a")
+                    .param("word list")
+                    .help("A word list to add the entries to")
+                
+                , help_arg(DESC).short("h")
+            ]))?;
+            
+            let res = cmd_scaffold(&document_path, &word_list_files, &exclusion_files, 
+                &markers, limit.as_ref(), output_path.as_ref(), append_path.as_ref());
+            Ok(report(res))
+        })
+        
+        , ArgDef::subcommand("coverage", |name, args| {
+            const DESC: &str = "
+                Reports how many of the hangeul characters and Korean words of a
+                document are covered by the word lists, and how many translated
+                words each word list contributed.
+            ";
+            
+            let mut document_path = String::new();
+            let mut word_list_files: Vec<String> = Vec::new();
+            let mut exclusion_files: Vec<String> = Vec::new();
+            let mut markers = MarkerArgs::default();
+            let mut summary_only = false;
+            
+            parse(name, args, with_marker_args(&mut markers, vec![
+                  ArgDef::positional("document", &mut document_path)
+                    .help("The document to measure")
+                
+                , ArgDef::collect("word-list", &mut word_list_files)
+                    .short("w")
+                    .param("file")
+                    .help("Word lists to read definitions from.")
+                
+                , ArgDef::collect("exclusion-rules", &mut exclusion_files)
+                    .short("x")
+                    .param("file")
+                    .help("Files with rules for words to exclude from the translation")
+                
+                , ArgDef::flag("summary", &mut summary_only)
+                    .short("s")
+                    .help("Only show the overall coverage, not that of each line")
+                
+                , help_arg(DESC).short("h")
+            ]))?;
+            
+            let res = cmd_coverage(&document_path, &word_list_files, &exclusion_files, 
+                &markers, summary_only);
+            Ok(report(res))
+        })
+        
+        , ArgDef::subcommand("status", |name, args| {
             const DESC: &str = "
+                Reports how much of a line mode document has been translated, and 
+                how many of its words are covered by the word lists.
+            ";
+            
+            let mut document_path = String::new();
+            let mut word_list_files: Vec<String> = Vec::new();
+            let mut markers = MarkerArgs::default();
             
+            parse(name, args, with_marker_args(&mut markers, vec![
+                  ArgDef::positional("document", &mut document_path)
+                    .help("A line mode document, or '-' to read it from stdin")
+                
+                , ArgDef::collect("word-list", &mut word_list_files)
+                    .short("w")
+                    .param("file")
+                    .help("Word lists to measure the dictionary coverage with.")
+                
+                , help_arg(DESC).short("h")
+            ]))?;
+            
+            let res = cmd_status(&document_path, &word_list_files, &markers);
+            Ok(report(res))
+        })
+        
+        , ArgDef::subcommand("romanize", |name, args| {
+            const DESC: &str = "
+                Writes the hangeul of a document with the latin alphabet.
             ";
             
             let mut document_path = String::new();
+            let mut system = String::from("rr");
             
             parse(name, args, vec![
                   ArgDef::positional("document", &mut document_path)
-                    .help("A document to translate")
-                  
+                    .help("A document to romanize")
+                
+                , ArgDef::setting("system", &mut system)
+                    .short("s")
+                    .help("The romanization system to use ('rr' or 'yale')")
+                
                 , help_arg(DESC).short("h")
             ])?;
             
-            let res = cmd_clean(&document_path);
-            Ok(res)
+            let system = match system.parse::<Romanization>() {
+                Ok(system) => system,
+                Err(e) => return Ok(report(Err(KorError::Args(e)))),
+            };
+            
+            let res = cmd_romanize(&document_path, system);
+            Ok(report(res))
+        })
+        
+        , ArgDef::subcommand("clean", |name, args| {
+            const DESC: &str = "
+            
+            ";
+            
+            let mut document_path = String::new();
+            let mut output_path: Option<String> = None;
+            let mut markers = MarkerArgs::default();
+            let mut layout = String::from("translation");
+            let mut use_auto = false;
+            let mut strict = false;
+            let mut memory_path: Option<String> = None;
+            
+            parse(name, args, with_marker_args(&mut markers, vec![
+                  ArgDef::positional("document", &mut document_path)
+                    .help("A document to translate")
+                
+                , ArgDef::option("output", &mut output_path)
+                    .short("o")
+                    .param("file")
+                    .help("A file to write the cleaned document to, instead of stdout")
+                
+                , ArgDef::setting("layout", &mut layout)
+                    .param("layout")
+                    .help("
+                        How the output is laid out: 'translation' (only the
+                        translations), 'alternating' (each source line followed by
+                        its translation) or 'side-by-side' (the source and the
+                        translation on one line, separated by a tab).
+                    ")
+                
+                , ArgDef::flag("use-auto", &mut use_auto)
+                    .short("a")
+                    .help("
+                        Use the automatic translation of lines without a manual
+                        translation.
+                    ")
+                
+                , ArgDef::flag("strict", &mut strict)
+                    .short("s")
+                    .help("Fail if any line is left untranslated.")
+                
+                , ArgDef::option("memory", &mut memory_path)
+                    .short("m")
+                    .param("file")
+                    .help("
+                        A translation memory to add the manual translations to. It is
+                        created if it doesn't exist, and kept with a '.bak' extension
+                        otherwise. Unreviewed memory matches are not added.
+                    ")
+                
+                , help_arg(DESC).short("h")
+            ]))?;
+            
+            let layout = match layout.parse::<CleanLayout>() {
+                Ok(layout) => layout,
+                Err(e) => return Ok(report(Err(KorError::Args(e)))),
+            };
+            let options = CleanOptions { layout, use_auto };
+            
+            let res = cmd_clean(&document_path, output_path.as_ref(), &markers, &options, 
+                strict, memory_path.as_ref());
+            Ok(report(res))
+        })
+        
+        , ArgDef::subcommand("export", |name, args| {
+            const DESC: &str = "
+                Exports the source lines of a line mode document and their manual
+                translations to a translation memory (TMX) or an XLIFF document.
+                The units are identified by the line numbers of their source lines.
+            ";
+            
+            let mut document_path = String::new();
+            let mut output_path: Option<String> = None;
+            let mut format = String::from("xliff");
+            let mut source_lang = String::from("ko");
+            let mut target_lang = String::from("en");
+            let mut markers = MarkerArgs::default();
+            
+            parse(name, args, with_marker_args(&mut markers, vec![
+                  ArgDef::positional("document", &mut document_path)
+                    .help("A line mode document to export ('-' for stdin)")
+                
+                , ArgDef::setting("format", &mut format)
+                    .short("f")
+                    .param("format")
+                    .help("The format to export to ('tmx' or 'xliff')")
+                
+                , ArgDef::option("output", &mut output_path)
+                    .short("o")
+                    .param("file")
+                    .help("A file to write the export to, instead of stdout")
+                
+                , ArgDef::setting("source-lang", &mut source_lang)
+                    .param("lang")
+                    .help("The language code of the source text (default: 'ko')")
+                
+                , ArgDef::setting("target-lang", &mut target_lang)
+                    .param("lang")
+                    .help("The language code of the translations (default: 'en')")
+                
+                , help_arg(DESC).short("h")
+            ]))?;
+            
+            let format = match format.parse::<ExchangeFormat>() {
+                Ok(format) => format,
+                Err(e) => return Ok(report(Err(KorError::Args(e)))),
+            };
+            
+            let res = cmd_export(&document_path, output_path.as_ref(), format, &source_lang, 
+                &target_lang, &markers);
+            Ok(report(res))
+        })
+        
+        , ArgDef::subcommand("import", |name, args| {
+            const DESC: &str = "
+                Fills in the manual translation lines of a line mode document with
+                the targets of an XLIFF document made with 'kor export'.
+            ";
+            
+            let mut document_path = String::new();
+            let mut xliff_path = String::new();
+            let mut output_path: Option<String> = None;
+            let mut in_place = false;
+            let mut markers = MarkerArgs::default();
+            
+            parse(name, args, with_marker_args(&mut markers, vec![
+                  ArgDef::positional("document", &mut document_path)
+                    .help("The line mode document the XLIFF was exported from")
+                
+                , ArgDef::positional("xliff", &mut xliff_path)
+                    .help("The translated XLIFF document")
+                
+                , ArgDef::option("output", &mut output_path)
+                    .short("o")
+                    .param("file")
+                    .help("A file to write the updated document to, instead of stdout")
+                
+                , ArgDef::flag("in-place", &mut in_place)
+                    .short("i")
+                    .help("
+                        Update the document itself, keeping the old version with a
+                        '.bak' extension.
+                    ")
+                
+                , help_arg(DESC).short("h")
+            ]))?;
+            
+            let res = cmd_import(&document_path, &xliff_path, output_path.as_ref(), &markers,
+                in_place);
+            Ok(report(res))
         })
         
         , help_arg(DESC).short("h")
         , version_arg()
     ]) {
-        Ok(_optional_error_code) => {},
-        Err(ParseError::Interrupted(_)) => {
-            return None;
-        },
-        Err(_) => {
-            return Some(1);
-        }
-    };
-    
-    None
+        Ok(exit_code) => exit_code,
+        Err(ParseError::Interrupted(_)) => None,
+        // The argument errors are reported by argonaut.
+        Err(_) => Some(1),
+    }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaffold_entries_are_loaded_without_meanings() {
+        let words = vec![
+            UnknownWord { word: "학교".into(), count: 2, example: "학교에 갔다.".into() },
+            UnknownWord { word: "친구".into(), count: 1, example: "친구를 만났다.".into() },
+        ];
+        let mut scaffold = Vec::new();
+        write_scaffold(&words, &mut scaffold).unwrap();
+        let text = format!("가다\n  to go\n{}", String::from_utf8(scaffold).unwrap());
+
+        let defs = read_definitions(&text);
+        assert_eq!(defs.len(), 3);
+        assert!(defs[1..].iter().all(|def| def.meanings.is_empty()));
+
+        let paths = vec![String::from("words.txt")];
+        let sources = vec![text.clone()];
+        let dict = load_dict(&paths, &sources).unwrap();
+        assert!(dict.find_longest_match("간다").is_some());
+        assert!(dict.find_longest_match("학교").is_none());
+        assert_eq!(kor::translate("학교", &dict), "학교");
+    }
+}
@@ -0,0 +1,266 @@
+//! Module for the translation memory, which keeps the manual translations
+//! of finished documents so they can be reused in new ones.
+
+use std::io::{self, Write};
+use std::cmp;
+use std::collections::HashMap;
+use std_unicode::str::UnicodeStr;
+use error::{KorError, FileKind};
+use normalize::nfc;
+use workfile::WorkFile;
+
+/// The minimum score of the fuzzy matches used by default.
+pub const MIN_MATCH_SCORE: u32 = 70;
+
+/// A source text and its translation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryEntry {
+    pub source: String,
+    /// The translation, with a line for each manual line.
+    pub target: String,
+}
+
+/// An entry found for a source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryMatch<'a> {
+    pub entry: &'a MemoryEntry,
+    /// How similar the source texts are, from 0 to 100 (exact).
+    pub score: u32,
+}
+
+/// A store of translations.
+/// The file format has an entry per line, with the source and its
+/// translation separated by a tab. Tabs, newlines and backslashes are
+/// written as '\t', '\n' and '\\', and a '#' starting a text as '\#'.
+/// Lines starting with '#' are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct TranslationMemory {
+    entries: Vec<MemoryEntry>,
+    /// The normalized source of each entry.
+    keys: Vec<String>,
+    /// The index of the entry for each normalized source.
+    index: HashMap<String, usize>,
+}
+
+/// Returns the text used to compare sources.
+fn normalize_source(source: &str) -> String {
+    nfc(source).split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Escapes the text for the memory file format.
+fn escape(text: &str) -> String {
+    let escaped = text.replace('\\', r"\\").replace('\t', r"\t").replace('\n', r"\n");
+    // A source starting with '#' would be read as a comment
+    if escaped.starts_with("#") { format!("\\{}", escaped) } else { escaped }
+}
+
+/// Reverses 'escape'.
+fn unescape(text: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('#') => unescaped.push('#'),
+            other => return Err(format!("Invalid escape: '\\{}'",
+                other.map(|ch| ch.to_string()).unwrap_or_default())),
+        }
+    }
+    Ok(unescaped)
+}
+
+/// Returns the number of character edits needed to turn one text into the
+/// other.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut prev = (0..b.len() + 1).collect::<Vec<_>>();
+    let mut cur = vec![0; b.len() + 1];
+    for i in 0..a.len() {
+        cur[0] = i + 1;
+        for j in 0..b.len() {
+            let substitution = prev[j] + if a[i] == b[j] { 0 } else { 1 };
+            cur[j + 1] = cmp::min(substitution, cmp::min(prev[j + 1], cur[j]) + 1);
+        }
+        ::std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+impl TranslationMemory {
+    /// Creates an empty translation memory.
+    pub fn new() -> TranslationMemory {
+        TranslationMemory::default()
+    }
+
+    /// Reads a translation memory file.
+    pub fn parse(text: &str, path: &str) -> Result<TranslationMemory, KorError> {
+        let mut memory = TranslationMemory::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.starts_with("#") || line.is_whitespace() {
+                continue;
+            }
+            let parse_error = |message: String| KorError::Parse {
+                kind: FileKind::Memory, path: path.to_string(), line: i+1, message,
+            };
+            let mut fields = line.split('\t');
+            let (source, target) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(source), Some(target), None) => (source, target),
+                _ => return Err(parse_error(format!("Expected '<source>\\t<translation>': {:?}",
+                    line))),
+            };
+            let source = unescape(source).map_err(&parse_error)?;
+            let target = unescape(target).map_err(&parse_error)?;
+            memory.add(&source, &target);
+        }
+        Ok(memory)
+    }
+
+    /// Returns the entries of the memory.
+    pub fn entries(&self) -> &[MemoryEntry] {
+        &self.entries
+    }
+
+    /// Adds a translation, replacing any earlier translation of the source.
+    pub fn add(&mut self, source: &str, target: &str) {
+        let entry = MemoryEntry { source: source.trim().to_string(), target: target.to_string() };
+        let key = normalize_source(source);
+        if let Some(&i) = self.index.get(&key) {
+            self.entries[i] = entry;
+            return;
+        }
+        self.index.insert(key.clone(), self.entries.len());
+        self.keys.push(key);
+        self.entries.push(entry);
+    }
+
+    /// Adds the manual translations of a line mode document.
+    /// Returns the number of translations added.
+    pub fn add_work_file(&mut self, work_file: &WorkFile) -> usize {
+        let mut added = 0;
+        for segment in work_file.segments().filter(|segment| segment.is_translated()) {
+            let target = segment.manual_lines()
+                .filter(|line| ! line.is_empty())
+                .map(|line| line.content())
+                .collect::<Vec<_>>()
+                .join("\n");
+            self.add(&segment.source, &target);
+            added += 1;
+        }
+        added
+    }
+
+    /// Finds the translation with the most similar source, if its score is
+    /// at least the given one.
+    pub fn find(&self, source: &str, min_score: u32) -> Option<MemoryMatch> {
+        let key = normalize_source(source);
+        if let Some(&i) = self.index.get(&key) {
+            return Some(MemoryMatch { entry: &self.entries[i], score: 100 });
+        }
+        let chars = key.chars().collect::<Vec<_>>();
+        let mut best: Option<MemoryMatch> = None;
+        for (i, entry_key) in self.keys.iter().enumerate() {
+            let entry_chars = entry_key.chars().collect::<Vec<_>>();
+            let longest = cmp::max(chars.len(), entry_chars.len());
+            let shortest = cmp::min(chars.len(), entry_chars.len());
+            // Skip the entries that are too long or short to be similar
+            if longest == 0 || (100 * shortest / longest) < min_score as usize {
+                continue;
+            }
+            let distance = edit_distance(&chars, &entry_chars);
+            // Only exact matches get a full score
+            let score = cmp::min(99, 100 * (longest - distance) / longest) as u32;
+            let better = best.map(|found| score > found.score).unwrap_or(true);
+            if score >= min_score && better {
+                best = Some(MemoryMatch { entry: &self.entries[i], score });
+            }
+        }
+        best
+    }
+
+    /// Writes the memory in its file format.
+    pub fn write(&self, out: &mut Write) -> io::Result<()> {
+        writeln!(out, "# kor translation memory: <source>\\t<translation>")?;
+        for entry in &self.entries {
+            writeln!(out, "{}\t{}", escape(&entry.source), escape(&entry.target))?;
+        }
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape("a\tb\nc\\d"), r"a\tb\nc\\d");
+        assert_eq!(escape("# 제1장"), r"\# 제1장");
+        assert_eq!(escape("1 # 2"), "1 # 2");
+        assert_eq!(unescape(r"a\tb\nc\\d").unwrap(), "a\tb\nc\\d");
+        assert_eq!(unescape(r"\# 제1장").unwrap(), "# 제1장");
+        assert!(unescape(r"a\x").is_err());
+        assert!(unescape("a\\").is_err());
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let mut memory = TranslationMemory::new();
+        memory.add("# 제1장", "# Chapter 1");
+        memory.add("학교에 갔다.", "I went to school.\nIt was\tfun.");
+        memory.add("a\\b", "c");
+        let mut text = Vec::new();
+        memory.write(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        let read = TranslationMemory::parse(&text, "memory.txt").unwrap();
+        assert_eq!(read.entries(), memory.entries());
+    }
+
+    #[test]
+    fn parse_skips_comments_and_reports_errors() {
+        let memory = TranslationMemory::parse("# comment\n\n가다\tto go\n", "m").unwrap();
+        assert_eq!(memory.entries(), &[MemoryEntry { 
+            source: String::from("가다"), target: String::from("to go") }]);
+        match TranslationMemory::parse("가다\tto go\n오다\n", "m") {
+            Err(KorError::Parse { line: 2, .. }) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        match TranslationMemory::parse("가다\tto \\go\n", "m") {
+            Err(KorError::Parse { line: 1, .. }) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn edit_distances() {
+        let chars = |text: &str| text.chars().collect::<Vec<_>>();
+        assert_eq!(edit_distance(&chars(""), &chars("abc")), 3);
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(edit_distance(&chars("간다"), &chars("갔다")), 1);
+    }
+
+    #[test]
+    fn fuzzy_scores() {
+        let mut memory = TranslationMemory::new();
+        memory.add("나는 학교에 간다", "I go to school");
+        memory.add("완전히 다른 문장이다", "A different sentence");
+
+        // Whitespace differences still give an exact match
+        let found = memory.find("  나는   학교에 간다 ", MIN_MATCH_SCORE).unwrap();
+        assert_eq!(found.score, 100);
+        assert_eq!(found.entry.target, "I go to school");
+
+        // One of nine characters differs
+        let found = memory.find("나는 학교에 갔다", MIN_MATCH_SCORE).unwrap();
+        assert_eq!(found.score, 88);
+        assert_eq!(found.entry.target, "I go to school");
+        assert!(memory.find("나는 학교에 갔다", 89).is_none());
+
+        assert!(memory.find("전혀 관계없는 말", MIN_MATCH_SCORE).is_none());
+        assert!(memory.find("", MIN_MATCH_SCORE).is_none());
+    }
+}
@@ -0,0 +1,183 @@
+//! Module for normalizing text to NFC, so that decomposed jamo and other
+//! decomposed characters match their composed forms.
+
+use std::borrow::Cow;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::canonical_combining_class;
+
+/// Returns the text normalized to NFC, borrowing it if it already is.
+pub fn nfc(text: &str) -> Cow<str> {
+    let normalized = text.nfc().collect::<String>();
+    if normalized == text {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(normalized)
+    }
+}
+
+/// Normalizes the text in place, only allocating if it changes.
+pub fn nfc_in_place(text: &mut Cow<str>) {
+    let normalized = match nfc(text) {
+        Cow::Owned(normalized) => Some(normalized),
+        Cow::Borrowed(_) => None,
+    };
+    if let Some(normalized) = normalized {
+        *text = Cow::Owned(normalized);
+    }
+}
+
+/// Returns whether the character may be combined with the ones before it
+/// when composing.
+fn combines_backwards(ch: char) -> bool {
+    match ch as u32 {
+        // Hangeul medial vowels and final consonants.
+        0x1160 ... 0x11FF | 0xD7B0 ... 0xD7FF => true,
+        _ => canonical_combining_class(ch) != 0,
+    }
+}
+
+/// A text normalized to NFC, which can map offsets in the normalized text
+/// back to the original one.
+#[derive(Debug, Clone)]
+pub struct Normalized<'src> {
+    original: &'src str,
+    /// The normalized text.
+    pub text: Cow<'src, str>,
+    /// The (normalized, original) offsets at the start of each chunk of
+    /// characters that are normalized together.
+    offsets: Vec<(usize, usize)>,
+}
+
+impl<'src> Normalized<'src> {
+    /// Normalizes the given text.
+    pub fn new(original: &'src str) -> Normalized<'src> {
+        let mut text = String::with_capacity(original.len());
+        let mut offsets = Vec::new();
+        let mut chunk_start = 0;
+        for (i, ch) in original.char_indices() {
+            if i != 0 && ! combines_backwards(ch) {
+                offsets.push((text.len(), chunk_start));
+                text.extend(original[chunk_start..i].nfc());
+                chunk_start = i;
+            }
+        }
+        if chunk_start < original.len() {
+            offsets.push((text.len(), chunk_start));
+            text.extend(original[chunk_start..].nfc());
+        }
+
+        if text == original {
+            Normalized { original, text: Cow::Borrowed(original), offsets: Vec::new() }
+        } else {
+            Normalized { original, text: Cow::Owned(text), offsets }
+        }
+    }
+
+    /// Returns whether normalizing changed the text.
+    pub fn is_changed(&self) -> bool {
+        match self.text {
+            Cow::Borrowed(_) => false,
+            Cow::Owned(_) => true,
+        }
+    }
+
+    /// Returns the offset in the original text matching the given offset in
+    /// the normalized one. Offsets inside a normalized chunk are mapped to
+    /// the start of the chunk.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        if ! self.is_changed() {
+            return offset;
+        } else if offset >= self.text.len() {
+            return self.original.len();
+        }
+        match self.offsets.binary_search_by(|&(start, _)| start.cmp(&offset)) {
+            Ok(i) => self.offsets[i].1,
+            Err(0) => 0,
+            Err(i) => self.offsets[i - 1].1,
+        }
+    }
+
+    /// Returns the part of the original text matching the given range in the
+    /// normalized text.
+    pub fn original_slice(&self, start: usize, end: usize) -> &'src str {
+        &self.original[self.original_offset(start)..self.original_offset(end)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dict::Dict;
+    use translate::{translate_iter, TranslationPart};
+
+    /// '각' decomposed into conjoining jamo.
+    const GAK_NFD: &str = "\u{1100}\u{1161}\u{11A8}";
+
+    #[test]
+    fn unchanged_text_is_borrowed() {
+        let normalized = Normalized::new("가다");
+        assert!(! normalized.is_changed());
+        assert_eq!(normalized.original_offset(3), 3);
+        assert_eq!(normalized.original_slice(0, 3), "가");
+    }
+
+    #[test]
+    fn conjoining_jamo_are_one_chunk() {
+        let normalized = Normalized::new(GAK_NFD);
+        assert!(normalized.is_changed());
+        assert_eq!(normalized.text, "각");
+        assert_eq!(normalized.offsets, vec![(0, 0)]);
+        assert_eq!(normalized.original_offset(0), 0);
+        assert_eq!(normalized.original_offset(3), GAK_NFD.len());
+        assert_eq!(normalized.original_slice(0, 3), GAK_NFD);
+
+        // A final consonant combines with the composed syllable before it
+        let normalized = Normalized::new("가\u{11A8}다");
+        assert_eq!(normalized.text, "각다");
+        assert_eq!(normalized.offsets, vec![(0, 0), (3, 6)]);
+        assert_eq!(normalized.original_slice(0, 3), "가\u{11A8}");
+        assert_eq!(normalized.original_slice(3, 6), "다");
+    }
+
+    #[test]
+    fn combining_marks_are_one_chunk() {
+        let normalized = Normalized::new("cafe\u{301}!");
+        assert_eq!(normalized.text, "caf\u{E9}!");
+        assert_eq!(normalized.offsets, vec![(0, 0), (1, 1), (2, 2), (3, 3), (5, 6)]);
+        assert_eq!(normalized.original_offset(5), 6);
+        assert_eq!(normalized.original_offset(6), 7);
+        assert_eq!(normalized.original_slice(3, 5), "e\u{301}");
+    }
+
+    #[test]
+    fn mixed_text_maps_to_original_offsets() {
+        let original = format!("가{}다", GAK_NFD);
+        let normalized = Normalized::new(&original);
+        assert_eq!(normalized.text, "가각다");
+        assert_eq!(normalized.offsets, vec![(0, 0), (3, 3), (6, 12)]);
+        assert_eq!(normalized.original_offset(3), 3);
+        assert_eq!(normalized.original_offset(6), 12);
+        assert_eq!(normalized.original_offset(9), original.len());
+        assert_eq!(normalized.original_slice(0, 3), "가");
+        assert_eq!(normalized.original_slice(3, 6), GAK_NFD);
+        assert_eq!(normalized.original_slice(6, 9), "다");
+    }
+
+    #[test]
+    fn translated_parts_are_original_text() {
+        let mut dict = Dict::new();
+        dict.add_word_list("각\n  angle\n");
+        let original = format!("그 {}도 각", GAK_NFD);
+        let mut parts = Vec::new();
+        translate_iter(&original, &dict, |part| parts.push(match part {
+            TranslationPart::Untranslated(src) => (src, None),
+            TranslationPart::Translated(src, def) => (src, Some(def.meanings[0].to_string())),
+        }));
+        assert_eq!(parts, vec![
+            ("그 ", None),
+            (GAK_NFD, Some("angle".to_string())),
+            ("도 ", None),
+            ("각", Some("angle".to_string())),
+        ]);
+    }
+}
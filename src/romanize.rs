@@ -0,0 +1,319 @@
+//! Module for writing hangeul with the latin alphabet.
+
+use std::str::FromStr;
+use hangeul2::{Block, Initial, Vowel, Final, is_hangeul};
+
+/// A romanization system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Romanization {
+    /// The Revised Romanization of Korean, which follows the pronunciation.
+    Revised,
+    /// The Yale romanization, which follows the spelling.
+    Yale,
+}
+
+impl FromStr for Romanization {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Romanization, String> {
+        match s.trim().to_lowercase().as_str() {
+            "rr" | "revised" => Ok(Romanization::Revised),
+            "yale" => Ok(Romanization::Yale),
+            other => Err(format!("Unknown romanization system: {:?} (expected 'rr' or 'yale')", other)),
+        }
+    }
+}
+
+/// Returns the hangeul syllable as a block of jamo, if it is one.
+fn block_of(ch: char) -> Option<Block> {
+    if is_hangeul(ch) { Some(Block::new(ch).unwrap()) } else { None }
+}
+
+/// Returns how an initial is written in the Revised Romanization.
+fn rr_initial(initial: Initial) -> &'static str {
+    use hangeul2::Initial::*;
+    match initial {
+        Giyeok => "g", SsangGiyeok => "kk", Nieun => "n", Digeut => "d", SsangDigeut => "tt",
+        Rieul => "r", Mieum => "m", Bieup => "b", SsangBieup => "pp", Siot => "s",
+        SsangSiot => "ss", Ieung => "", Jieut => "j", SsangJieut => "jj", Chieut => "ch",
+        Kieuk => "k", Tieut => "t", Pieup => "p", Hieut => "h",
+    }
+}
+
+/// Returns how a vowel is written in the Revised Romanization.
+fn rr_vowel(vowel: Vowel) -> &'static str {
+    use hangeul2::Vowel::*;
+    match vowel {
+        A => "a", Ae => "ae", Ya => "ya", Yae => "yae", Eo => "eo", E => "e", Yeo => "yeo",
+        Ye => "ye", O => "o", Wa => "wa", Wae => "wae", Oe => "oe", Yo => "yo", U => "u",
+        Weo => "wo", We => "we", Wi => "wi", Yu => "yu", Y => "eu", Yi => "ui", I => "i",
+    }
+}
+
+/// Returns the final that a final is pronounced as before a consonant, or at
+/// the end of a word.
+fn representative(final_: Final) -> Final {
+    use hangeul2::Final::*;
+    match final_ {
+        G | Gg | Gs | Lg | K => G,
+        N | Nj | Nh => N,
+        D | S | Ss | J | Ch | T | H => D,
+        L | Lb | Ls | Lt | Lh => L,
+        M | Lm => M,
+        B | Bs | Lp | P => B,
+        Ng => Ng,
+        _ => Empty,
+    }
+}
+
+/// Returns how a final is written in the Revised Romanization, before a
+/// consonant or at the end of a word.
+fn rr_final(final_: Final) -> &'static str {
+    use hangeul2::Final::*;
+    match representative(final_) {
+        G => "k",
+        N => "n",
+        D => "t",
+        L => "l",
+        M => "m",
+        B => "p",
+        Ng => "ng",
+        _ => "",
+    }
+}
+
+/// Returns the part of a final that stays, and the part that moves to the
+/// next syllable, when it starts with a vowel.
+fn rr_liaison(final_: Final) -> (&'static str, &'static str) {
+    use hangeul2::Final::*;
+    match final_ {
+        G => ("", "g"), Gg => ("", "kk"), Gs => ("k", "s"),
+        N => ("", "n"), Nj => ("n", "j"), Nh => ("", "n"),
+        D => ("", "d"),
+        L => ("", "r"), Lg => ("l", "g"), Lm => ("l", "m"), Lb => ("l", "b"),
+        Ls => ("l", "s"), Lt => ("l", "t"), Lp => ("l", "p"), Lh => ("", "r"),
+        M => ("", "m"), B => ("", "b"), Bs => ("p", "s"),
+        S => ("", "s"), Ss => ("", "ss"), Ng => ("ng", ""),
+        J => ("", "j"), Ch => ("", "ch"), K => ("", "k"), T => ("", "t"), P => ("", "p"),
+        H | Empty => ("", ""),
+    }
+}
+
+/// Returns how a final and the initial of the following syllable are
+/// written in the Revised Romanization, after sound changes.
+fn rr_link(final_: Final, initial: Initial, next_vowel: Vowel) -> (&'static str, &'static str) {
+    use hangeul2::Final::*;
+    let before_i = match next_vowel { Vowel::I => true, _ => false };
+    let next = rr_initial(initial);
+    match initial {
+        // Liaison, with palatalization of 'ㄷ' and 'ㅌ' before 'ㅣ'.
+        Initial::Ieung => match final_ {
+            D if before_i => ("", "j"),
+            T if before_i => ("", "ch"),
+            Lt if before_i => ("l", "ch"),
+            _ => rr_liaison(final_),
+        },
+        // Aspiration.
+        Initial::Hieut => match final_ {
+            G | Gg | Gs | K => ("", "k"),
+            Lg => ("l", "k"),
+            D | T if before_i => ("", "ch"),
+            D | S | Ss | T => ("", "t"),
+            J | Ch => ("", "ch"),
+            Nj => ("n", "ch"),
+            B | Bs | P => ("", "p"),
+            Lb => ("l", "p"),
+            Nh => ("n", ""),
+            Lh => ("", "r"),
+            H => ("", "h"),
+            _ => (rr_final(final_), "h"),
+        },
+        // A final 'ㅎ' aspirates the next consonant.
+        Initial::Giyeok | Initial::Digeut | Initial::Jieut => {
+            let aspirated = match initial {
+                Initial::Giyeok => "k",
+                Initial::Digeut => "t",
+                _ => "ch",
+            };
+            match final_ {
+                H => ("", aspirated),
+                Nh => ("n", aspirated),
+                Lh => ("l", aspirated),
+                _ => (rr_final(final_), next),
+            }
+        }
+        // Nasalization.
+        Initial::Nieun | Initial::Mieum => match (final_, initial) {
+            (L, Initial::Nieun) | (Lh, Initial::Nieun) => ("l", "l"),
+            (H, _) | (Nh, _) => ("n", next),
+            _ => match representative(final_) {
+                G => ("ng", next),
+                D => ("n", next),
+                B => ("m", next),
+                _ => (rr_final(final_), next),
+            },
+        },
+        // 'ㄹ' is pronounced as 'ㄴ' after most consonants.
+        Initial::Rieul => match final_ {
+            Empty => ("", "r"),
+            N | L => ("l", "l"),
+            _ => match representative(final_) {
+                G | Ng => ("ng", "n"),
+                D => ("n", "n"),
+                B | M => ("m", "n"),
+                _ => (rr_final(final_), "n"),
+            },
+        },
+        _ => match final_ {
+            H => ("", next),
+            _ => (rr_final(final_), next),
+        },
+    }
+}
+
+/// Returns how an initial is written in the Yale romanization.
+fn yale_initial(initial: Initial) -> &'static str {
+    use hangeul2::Initial::*;
+    match initial {
+        Giyeok => "k", SsangGiyeok => "kk", Nieun => "n", Digeut => "t", SsangDigeut => "tt",
+        Rieul => "l", Mieum => "m", Bieup => "p", SsangBieup => "pp", Siot => "s",
+        SsangSiot => "ss", Ieung => "", Jieut => "c", SsangJieut => "cc", Chieut => "ch",
+        Kieuk => "kh", Tieut => "th", Pieup => "ph", Hieut => "h",
+    }
+}
+
+/// Returns how a vowel is written in the Yale romanization.
+fn yale_vowel(vowel: Vowel) -> &'static str {
+    use hangeul2::Vowel::*;
+    match vowel {
+        A => "a", Ae => "ay", Ya => "ya", Yae => "yay", Eo => "e", E => "ey", Yeo => "ye",
+        Ye => "yey", O => "o", Wa => "wa", Wae => "way", Oe => "oy", Yo => "yo", U => "wu",
+        Weo => "we", We => "wey", Wi => "wi", Yu => "yu", Y => "u", Yi => "uy", I => "i",
+    }
+}
+
+/// Returns how a final is written in the Yale romanization.
+fn yale_final(final_: Final) -> &'static str {
+    use hangeul2::Final::*;
+    match final_ {
+        Empty => "", G => "k", Gg => "kk", Gs => "ks", N => "n", Nj => "nc", Nh => "nh",
+        D => "t", L => "l", Lg => "lk", Lm => "lm", Lb => "lp", Ls => "ls", Lt => "lth",
+        Lp => "lph", Lh => "lh", M => "m", B => "p", Bs => "ps", S => "s", Ss => "ss",
+        Ng => "ng", J => "c", Ch => "ch", K => "kh", T => "th", P => "ph", H => "h",
+    }
+}
+
+/// Writes the given text using the Revised Romanization of Korean.
+/// Sound changes are applied between the syllables of each word.
+pub fn romanize_revised(text: &str) -> String {
+    let mut romanized = String::with_capacity(text.len() * 2);
+    let mut chars = text.chars().peekable();
+    // The initial of the current syllable, as changed by the previous one.
+    let mut linked_initial: Option<&str> = None;
+    while let Some(ch) = chars.next() {
+        let block = if let Some(block) = block_of(ch) {
+            block
+        } else {
+            romanized.push(ch);
+            linked_initial = None;
+            continue;
+        };
+        romanized.push_str(linked_initial.take().unwrap_or(rr_initial(block.initial)));
+        romanized.push_str(rr_vowel(block.vowel));
+        match chars.peek().and_then(|&next| block_of(next)) {
+            Some(next) => {
+                let (final_part, next_part) = rr_link(block.final_, next.initial, next.vowel);
+                romanized.push_str(final_part);
+                linked_initial = Some(next_part);
+            }
+            None => {
+                romanized.push_str(rr_final(block.final_));
+            }
+        }
+    }
+    romanized
+}
+
+/// Writes the given text using the Yale romanization.
+/// A '.' separates syllables where the spelling would be ambiguous.
+pub fn romanize_yale(text: &str) -> String {
+    let mut romanized = String::with_capacity(text.len() * 2);
+    let mut after_final = false;
+    for ch in text.chars() {
+        let block = if let Some(block) = block_of(ch) {
+            block
+        } else {
+            romanized.push(ch);
+            after_final = false;
+            continue;
+        };
+        if let Initial::Ieung = block.initial {
+            if after_final {
+                romanized.push('.');
+            }
+        }
+        romanized.push_str(yale_initial(block.initial));
+        romanized.push_str(yale_vowel(block.vowel));
+        romanized.push_str(yale_final(block.final_));
+        after_final = match block.final_ { Final::Empty => false, _ => true };
+    }
+    romanized
+}
+
+/// Writes the given text using the given romanization system.
+/// Characters other than hangeul syllables are kept as they are.
+pub fn romanize(text: &str, system: Romanization) -> String {
+    match system {
+        Romanization::Revised => romanize_revised(text),
+        Romanization::Yale => romanize_yale(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn revised_liaison() {
+        assert_eq!(romanize_revised("한국어"), "hangugeo");
+        assert_eq!(romanize_revised("읽어"), "ilgeo");
+        assert_eq!(romanize_revised("좋아"), "joa");
+        assert_eq!(romanize_revised("같이"), "gachi");
+        assert_eq!(romanize_revised("굳이"), "guji");
+    }
+
+    #[test]
+    fn revised_assimilation() {
+        // Nasalization
+        assert_eq!(romanize_revised("국물"), "gungmul");
+        assert_eq!(romanize_revised("입니다"), "imnida");
+        // 'ㄹ' after consonants
+        assert_eq!(romanize_revised("독립"), "dongnip");
+        assert_eq!(romanize_revised("종로"), "jongno");
+        assert_eq!(romanize_revised("신라"), "silla");
+        // Aspiration
+        assert_eq!(romanize_revised("좋다"), "jota");
+        assert_eq!(romanize_revised("축하"), "chuka");
+        assert_eq!(romanize_revised("닫히다"), "dachida");
+    }
+
+    #[test]
+    fn revised_word_boundaries() {
+        assert_eq!(romanize_revised("한국 말"), "hanguk mal");
+        assert_eq!(romanize_revised("밥."), "bap.");
+    }
+
+    #[test]
+    fn yale_follows_spelling() {
+        assert_eq!(romanize_yale("한국어"), "hankwuk.e");
+        assert_eq!(romanize_yale("독립"), "toklip");
+        assert_eq!(romanize_yale("같이"), "kath.i");
+        assert_eq!(romanize_yale("아이"), "ai");
+        assert_eq!(romanize_yale("서울"), "sewul");
+    }
+
+    #[test]
+    fn other_characters_are_kept() {
+        assert_eq!(romanize("A 한", Romanization::Revised), "A han");
+        assert_eq!(romanize("A 한", Romanization::Yale), "A han");
+    }
+}
@@ -1,7 +1,13 @@
 //! Module for translating text using a dictionary.
 
+use std::io::{self, Read, Write, BufRead, BufReader};
 use dict::Dict;
 use wordlist::Def;
+use hanja::{self, HanjaTable};
+use romanize::{romanize, Romanization};
+use normalize::Normalized;
+use memory::{TranslationMemory, MIN_MATCH_SCORE};
+use exclusion::ExclusionOverlay;
 
 #[derive(Debug)]
 pub enum TranslationPart<'def, 'src, 'defsrc: 'def> {
@@ -13,18 +19,30 @@ pub enum TranslationPart<'def, 'src, 'defsrc: 'def> {
 
 /// Attempts to replace as many words in the given text as possible with their
 /// definition in the dictionary, and sends the parts to the given handler.
-pub fn translate_iter<'src, 'def, 'defsrc, F>(text: &'src str, dict: &'def Dict<'defsrc>, mut handle_part: F) 
+/// The text is normalized to NFC for the matching, but the parts are taken
+/// from the original text.
+pub fn translate_iter<'src, 'def, 'defsrc, F>(text: &'src str, dict: &'def Dict<'defsrc>, handle_part: F) 
+        where F: FnMut(TranslationPart<'def, 'src, 'defsrc>) {
+    translate_iter_excluding(text, dict, &ExclusionOverlay::new(), handle_part);
+}
+
+/// Splits the text into parts like 'translate_iter', but also leaves the
+/// words excluded by the rules of the overlay untranslated.
+pub fn translate_iter_excluding<'src, 'def, 'defsrc, F>(text: &'src str, 
+        dict: &'def Dict<'defsrc>, overlay: &ExclusionOverlay, mut handle_part: F) 
         where F: FnMut(TranslationPart<'def, 'src, 'defsrc>) {
     use self::TranslationPart::*;
+    let normalized = Normalized::new(text);
+    let norm_text: &str = &normalized.text;
     let mut untranslated_start = None;
     let mut start = 0;
-    while start < text.len() {
-        let rem = &text[start..];
-        if let Some((prefix, def)) = dict.find_longest_match(rem) {
+    while start < norm_text.len() {
+        let rem = &norm_text[start..];
+        if let Some((prefix, def)) = dict.find_match(norm_text, start, overlay) {
             if let Some(u) = untranslated_start.take() {
-                handle_part(Untranslated(&text[u..start]));
+                handle_part(Untranslated(normalized.original_slice(u, start)));
             }
-            handle_part(Translated(prefix, def));
+            handle_part(Translated(normalized.original_slice(start, start + prefix.len()), def));
             start += prefix.len();
         } else {
             untranslated_start = untranslated_start.take().or_else(|| Some(start));
@@ -32,40 +50,139 @@ pub fn translate_iter<'src, 'def, 'defsrc, F>(text: &'src str, dict: &'def Dict<
         }
     }
     if let Some(u) = untranslated_start.take() {
-        handle_part(Untranslated(&text[u..start]));
+        handle_part(Untranslated(normalized.original_slice(u, start)));
+    }
+}
+
+/// Options for how translated text is written.
+#[derive(Debug, Clone)]
+pub struct TranslateOptions<'t> {
+    /// A table used to annotate unknown Sino-Korean words with the meanings
+    /// of their hanja.
+    pub hanja_table: Option<&'t HanjaTable>,
+    /// Whether to show the hangeul reading of translated hanja.
+    pub show_hanja_reading: bool,
+    /// A romanization to show for translated words.
+    pub romanization: Option<Romanization>,
+    /// A translation memory used to fill in the manual lines of line mode
+    /// documents.
+    pub memory: Option<&'t TranslationMemory>,
+    /// The minimum score of the translation memory matches that are used.
+    pub min_match_score: u32,
+    /// Exclusion rules applied on top of those of the dictionary, like the
+    /// directives of a document.
+    pub exclusions: ExclusionOverlay,
+}
+
+impl<'t> Default for TranslateOptions<'t> {
+    fn default() -> TranslateOptions<'t> {
+        TranslateOptions {
+            hanja_table: None,
+            show_hanja_reading: false,
+            romanization: None,
+            memory: None,
+            min_match_score: MIN_MATCH_SCORE,
+            exclusions: ExclusionOverlay::new(),
+        }
     }
 }
 
 /// Replaces as much of text with the meanings found in the dictionary
 /// as possible.
 pub fn translate(text: &str, dict: &Dict) -> String {
+    translate_with_options(text, dict, &TranslateOptions::default())
+}
+
+/// Adds the text of a translated part to the output.
+fn push_part(part: TranslationPart, dict: &Dict, options: &TranslateOptions, 
+        translated: &mut String) {
     use self::TranslationPart::*;
-    let mut translated = String::with_capacity(text.len());
-    translate_iter(text, dict, |part| {
-        match part {
-            Untranslated(src) => {
+    match part {
+        Untranslated(src) => {
+            if let Some(table) = options.hanja_table {
+                hanja::annotate_untranslated(src, dict, table, translated);
+            } else {
                 translated.push_str(src);
             }
-            Translated(src, def) => {
-                let meaning = &def.meanings[0];
-                if meaning.starts_with("{") {
-                    translated.push('{');
+        }
+        Translated(src, def) => {
+            // Definitions without meanings leave the word untranslated
+            let meaning = match def.meanings.first() {
+                Some(meaning) => meaning,
+                None => {
                     translated.push_str(src);
-                    //translated.push_str(": ");
-                    //translated.push_str(&meaning[1..]);
-                } else if meaning.starts_with("<") {
-                    translated.push_str(meaning);
-                } else {
-                    translated.push('[');
-                    translated.push_str(meaning);
-                    if def.hangeul.ends_with("다") {
-                        //translated.push_str(": ");
-                        //translated.push_str(src);
+                    return;
+                }
+            };
+            if meaning.starts_with("{") {
+                translated.push('{');
+                translated.push_str(src);
+                //translated.push_str(": ");
+                //translated.push_str(&meaning[1..]);
+            } else if meaning.starts_with("<") {
+                translated.push_str(meaning);
+            } else {
+                translated.push('[');
+                translated.push_str(meaning);
+                let has_hanja = src.chars().any(hanja::is_hanja);
+                if options.show_hanja_reading && has_hanja {
+                    translated.push_str(": ");
+                    translated.push_str(&hanja::reading_of(src, def));
+                }
+                if let Some(system) = options.romanization {
+                    translated.push_str(" / ");
+                    if has_hanja {
+                        translated.push_str(&romanize(&hanja::reading_of(src, def), system));
+                    } else {
+                        translated.push_str(&romanize(src, system));
                     }
-                    translated.push(']');
                 }
+                if def.hangeul.ends_with("다") {
+                    //translated.push_str(": ");
+                    //translated.push_str(src);
+                }
+                translated.push(']');
             }
         }
+    }
+}
+
+/// Replaces as much of text with the meanings found in the dictionary
+/// as possible, using the given options.
+pub fn translate_with_options(text: &str, dict: &Dict, options: &TranslateOptions) -> String {
+    let mut translated = String::with_capacity(text.len());
+    translate_iter_excluding(text, dict, &options.exclusions, |part| {
+        push_part(part, dict, options, &mut translated);
     });
     translated
 }
+
+/// Translates the text like 'translate_with_options', writing each part to
+/// the given output as it is translated.
+pub fn translate_writer<W: Write + ?Sized>(text: &str, dict: &Dict, options: &TranslateOptions, 
+        out: &mut W) -> io::Result<()> {
+    let mut result = Ok(());
+    let mut buffer = String::new();
+    translate_iter_excluding(text, dict, &options.exclusions, |part| {
+        if result.is_err() {
+            return;
+        }
+        buffer.clear();
+        push_part(part, dict, options, &mut buffer);
+        result = out.write_all(buffer.as_bytes());
+    });
+    result
+}
+
+/// Translates the text read from the input line by line, and writes it to
+/// the output, so that the whole text never has to be kept in memory.
+pub fn translate_reader<R: Read, W: Write + ?Sized>(input: R, dict: &Dict, 
+        options: &TranslateOptions, out: &mut W) -> io::Result<()> {
+    let mut input = BufReader::new(input);
+    let mut line = String::new();
+    while input.read_line(&mut line)? != 0 {
+        translate_writer(&line, dict, options, out)?;
+        line.clear();
+    }
+    out.flush()
+}
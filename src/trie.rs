@@ -22,8 +22,45 @@ impl<T: Debug> Trie<T> {
         }) 
     }
     
-    pub fn insert(&mut self, key: &str, value: T) {
-        self.top.insert(key, value);
+    /// Finds the values of every prefix of the key, the shortest first.
+    pub fn find_all_matches<'n, 'k>(&'n self, key: &'k str) -> Vec<(&'k str, &'n T)> {
+        let mut found = Vec::new();
+        let mut node = &self.top;
+        for (i, ch) in key.char_indices() {
+            if let Some(child) = node.leaves.get(&ch) {
+                if let Some(ref value) = child.value {
+                    found.push((&key[.. i + ch.len_utf8()], value));
+                }
+                node = child;
+            } else {
+                break;
+            }
+        }
+        found
+    }
+    
+    /// Inserts a value, and returns the one it replaced, if any.
+    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
+        self.top.insert(key, value)
+    }
+    
+    /// Returns the value of the key, if any.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        let mut node = &self.top;
+        for ch in key.chars() {
+            match node.leaves.get(&ch) {
+                Some(child) => node = child,
+                None => return None,
+            }
+        }
+        node.value.as_ref()
+    }
+    
+    /// Removes the values that the function returns false for.
+    /// Returns the number of removed values.
+    pub fn retain<F: FnMut(&str, &T) -> bool>(&mut self, mut keep: F) -> usize {
+        let mut key = String::new();
+        self.top.retain(&mut key, &mut keep)
     }
     
     pub fn remove(&mut self, key: &str) -> Option<T> {
@@ -83,13 +120,31 @@ impl<T: Debug> TrieNode<T> {
         }
     }
     
-    fn insert(&mut self, key: &str, value: T) {
+    fn retain<F: FnMut(&str, &T) -> bool>(&mut self, key: &mut String, keep: &mut F) -> usize {
+        let mut removed = 0;
+        let remove = match self.value {
+            Some(ref value) => ! keep(&key[..], value),
+            None => false,
+        };
+        if remove {
+            self.value = None;
+            removed += 1;
+        }
+        for (&ch, child) in &mut self.leaves {
+            key.push(ch);
+            removed += child.retain(key, keep);
+            key.pop();
+        }
+        removed
+    }
+    
+    fn insert(&mut self, key: &str, value: T) -> Option<T> {
         if key.len() == 0 {
-            self.value = Some(value);
+            ::std::mem::replace(&mut self.value, Some(value))
         } else {
             let ch = key.chars().nth(0).unwrap();
             let rem = &key[ch.len_utf8()..];
-            self.leaves.entry(ch).or_insert_with(|| Box::new(TrieNode::new())).insert(rem, value);
+            self.leaves.entry(ch).or_insert_with(|| Box::new(TrieNode::new())).insert(rem, value)
         }
     }
 }
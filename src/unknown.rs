@@ -0,0 +1,167 @@
+//! Module for finding the words of a text that aren't in the dictionary.
+
+use std::io::{self, Write};
+use std::collections::HashMap;
+use hangeul2::is_hangeul;
+use dict::Dict;
+use translate::{TranslationPart, translate_iter_excluding};
+use exclusion::ExclusionOverlay;
+use config::Markers;
+
+/// Common particles, which are removed from the end of unknown words.
+/// The longest ones come first, so that they are removed whole.
+const PARTICLES: &[&str] = &[
+    "에서는", "에게서", "으로는", "으로써", "에서", "에게", "한테", "까지", "부터", "보다",
+    "처럼", "으로", "이나", "이랑", "하고", "은", "는", "이", "가", "을", "를", "에", "의",
+    "도", "로", "와", "과", "만", "랑",
+];
+
+/// A word that isn't in the dictionary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownWord {
+    pub word: String,
+    /// How many times the word was found.
+    pub count: usize,
+    /// The first line the word was found in.
+    pub example: String,
+}
+
+/// Removes a common particle from the end of a word, if the rest is long
+/// enough to still be a word. Words of the dictionary are kept whole.
+pub fn strip_particle<'w>(word: &'w str, dict: &Dict) -> &'w str {
+    if dict.get(word).is_some() {
+        return word;
+    }
+    let len = word.chars().count();
+    for particle in PARTICLES {
+        let particle_len = particle.chars().count();
+        // Single syllables are too often just the end of a word, like in
+        // '아이', so at least two syllables have to be left
+        let min_len = if particle_len == 1 { 2 } else { 1 };
+        if word.ends_with(particle) && len >= particle_len + min_len {
+            return &word[..word.len() - particle.len()];
+        }
+    }
+    word
+}
+
+/// Finds the hangeul words of the text that the dictionary doesn't
+/// translate, without their particles, the most frequent first.
+/// Untranslated text right after a translated word is taken to be its
+/// ending, and is skipped. Exclusion directives in the text apply to the
+/// lines after them.
+pub fn find_unknown_words(text: &str, dict: &Dict, markers: &Markers) -> Vec<UnknownWord> {
+    let mut found: HashMap<String, UnknownWord> = HashMap::new();
+    let mut exclusions = ExclusionOverlay::new();
+    for line in text.lines() {
+        if exclusions.apply_directive(line, &markers.comment_prefix) {
+            continue;
+        }
+        let mut after_translated = false;
+        translate_iter_excluding(line, dict, &exclusions, |part| {
+            let src = match part {
+                TranslationPart::Untranslated(src) => src,
+                TranslationPart::Translated(..) => {
+                    after_translated = true;
+                    return;
+                }
+            };
+            let mut words = Vec::new();
+            let mut start = None;
+            for (i, ch) in src.char_indices().chain(Some((src.len(), ' '))) {
+                match (start, is_hangeul(ch)) {
+                    (None, true) => start = Some(i),
+                    (Some(s), false) => {
+                        words.push((s, &src[s..i]));
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+            for (pos, word) in words {
+                if pos == 0 && after_translated {
+                    continue;
+                }
+                let word = strip_particle(word, dict);
+                let entry = found.entry(word.to_string()).or_insert_with(|| UnknownWord {
+                    word: word.to_string(), count: 0, example: line.trim().to_string(),
+                });
+                entry.count += 1;
+            }
+            after_translated = false;
+        });
+    }
+    let mut words = found.into_iter().map(|(_, word)| word).collect::<Vec<_>>();
+    words.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.word.cmp(&b.word)));
+    words
+}
+
+/// Writes a word list entry for each word, with an empty meaning to fill in
+/// and the line it was found in as a comment.
+/// Ex:
+/// # 학교에 갔다.
+/// 학교
+///   
+pub fn write_scaffold(words: &[UnknownWord], out: &mut Write) -> io::Result<()> {
+    for word in words {
+        writeln!(out, "")?;
+        writeln!(out, "# {}", word.example)?;
+        writeln!(out, "{}", word.word)?;
+        writeln!(out, "  ")?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_words_are_kept() {
+        let dict = Dict::new();
+        assert_eq!(strip_particle("아이", &dict), "아이");
+        assert_eq!(strip_particle("나이", &dict), "나이");
+        assert_eq!(strip_particle("사과", &dict), "사과");
+    }
+
+    #[test]
+    fn particles_are_stripped() {
+        let dict = Dict::new();
+        assert_eq!(strip_particle("아이가", &dict), "아이");
+        assert_eq!(strip_particle("나이를", &dict), "나이");
+        assert_eq!(strip_particle("집에서", &dict), "집");
+        assert_eq!(strip_particle("학교에서는", &dict), "학교");
+    }
+
+    #[test]
+    fn words_of_the_dictionary_are_kept() {
+        let mut dict = Dict::new();
+        assert_eq!(strip_particle("고양이", &dict), "고양");
+        dict.add_word_list("고양이\n  cat\n");
+        assert_eq!(strip_particle("고양이", &dict), "고양이");
+    }
+
+    #[test]
+    fn unknown_words_are_counted_without_particles() {
+        let dict = Dict::new();
+        let text = "아이가 웃었다. 아이는 나이가 많다.";
+        let words = find_unknown_words(text, &dict, &Markers::default());
+        assert_eq!(words[0], UnknownWord { 
+            word: String::from("아이"), count: 2, example: String::from(text) });
+        let rest = words[1..].iter().map(|word| word.word.as_str()).collect::<Vec<_>>();
+        assert_eq!(rest, vec!["나이", "많다", "웃었다"]);
+    }
+
+    #[test]
+    fn directives_exclude_words() {
+        let mut dict = Dict::new();
+        dict.add_word_list("학교\n  school\n");
+        let text = "학교에 갔다.\n-# exclude: 학교\n학교에 갔다.";
+        let words = find_unknown_words(text, &dict, &Markers::default());
+        assert_eq!(words[0].word, "갔다");
+        assert_eq!(words[0].count, 2);
+        assert_eq!(words[1].word, "학교");
+        assert_eq!(words[1].count, 1);
+        assert_eq!(words.len(), 2);
+    }
+}
@@ -1,6 +1,7 @@
 //! Module for working with word list files and data.
 
 use std::borrow::Cow;
+use std::fmt;
 use regex::Regex;
 use common::*;
 use std_unicode::str::UnicodeStr;
@@ -19,8 +20,107 @@ lazy_static! {
 pub struct Def<'src> {
     pub hangeul: Cow<'src, str>,
     pub aliases: Vec<Cow<'src, str>>,
-    pub hanja: Option<Cow<'src, str>>,
+    pub hanja: Vec<Hanja<'src>>,
     pub meanings: Vec<Cow<'src, str>>,
+    /// The line of the word list the definition starts on, or 0 if unknown.
+    pub line: usize,
+    /// The index of the word list the definition was read from, among those
+    /// added to its dictionary, if known.
+    pub source: Option<usize>,
+}
+
+/// A hanja spelling of a definition.
+/// Ex: '幼稚-' is the hanja '幼稚', with the rest of the word ('하다')
+/// attached after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hanja<'src> {
+    /// The hanja, without markers.
+    pub text: Cow<'src, str>,
+    /// Whether hangeul attaches before the hanja (marked like '-的').
+    pub attached_before: bool,
+    /// Whether hangeul attaches after the hanja (marked like '幼稚-').
+    pub attached_after: bool,
+}
+
+impl<'src> fmt::Display for Hanja<'src> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.attached_before {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", self.text)?;
+        if self.attached_after {
+            write!(f, "-")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'src> Def<'src> {
+    /// Returns the first line of the definition in the word list format.
+    /// Ex: '유치하다 | 유치한 (幼稚- | 幼穉-)'.
+    pub fn head(&self) -> String {
+        let mut head = self.hangeul.to_string();
+        for alias in &self.aliases {
+            head.push_str(" | ");
+            head.push_str(alias);
+        }
+        if ! self.hanja.is_empty() {
+            let hanja = self.hanja.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+            head.push_str(&format!(" ({})", hanja.join(" | ")));
+        }
+        head
+    }
+
+    /// Returns whether the definitions are the same, without comparing where
+    /// they were read from.
+    pub fn same_as(&self, other: &Def) -> bool {
+        self.hangeul == other.hangeul && self.aliases == other.aliases 
+            && self.hanja == other.hanja && self.meanings == other.meanings
+    }
+}
+
+/// Writes a meaning line in the word list format.
+fn write_meaning(meaning: &str, f: &mut fmt::Formatter) -> fmt::Result {
+    // Meanings indented by two spaces and a '#' would be read as comments
+    if meaning.starts_with("#") {
+        write!(f, "\n   {}", meaning)
+    } else {
+        write!(f, "\n  {}", meaning)
+    }
+}
+
+/// Writes the definition in the word list format, with a line for the
+/// hangeul, aliases and hanja, and an indented line for each meaning.
+impl<'src> fmt::Display for Def<'src> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.head())?;
+        for meaning in &self.meanings {
+            write_meaning(meaning, f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether the character marks where hangeul attaches to hanja.
+fn is_attachment_marker(ch: char) -> bool {
+    ch == '-' || ch == '~' || ch.is_whitespace()
+}
+
+/// Reads the alternatives of the hanja part of a word list definition.
+/// Ex: '幼稚- | 幼穉-' => ['幼稚-', '幼穉-'].
+fn read_hanja<'src>(group: &'src str) -> Vec<Hanja<'src>> {
+    group.split("|").filter_map(|variant| {
+        let variant = variant.trim();
+        let text = variant.trim_matches(is_attachment_marker);
+        if text.is_empty() {
+            return None;
+        }
+        Some(Hanja {
+            text: text.into(),
+            attached_before: variant.starts_with(|ch| ch == '-' || ch == '~'),
+            attached_after: variant.ends_with(|ch| ch == '-' || ch == '~'),
+        })
+    }).collect()
 }
 
 /// Cleans the hangeul part of a word list definition.
@@ -46,8 +146,9 @@ fn read_definition<'src>(line: &'src str, lineno: usize) -> Option<Def<'src>> {
     let mut parts = hangeul_blocks.split("|").map(|s| clean_hangeul(s).into());
     let hangeul = parts.next().unwrap();
     let aliases = parts.collect::<Vec<_>>();
-    let hanja = caps.get(2).map(|m| m.as_str().into());
-    Some(Def { hangeul, aliases, hanja, meanings: Vec::new() })
+    let hanja = caps.get(2).map(|m| read_hanja(m.as_str())).unwrap_or_else(Vec::new);
+    Some(Def { hangeul, aliases, hanja, meanings: Vec::new(), line: lineno, 
+        source: None })
 }
 
 /// Reads a meaning from a line in a word-list.
@@ -55,27 +156,51 @@ fn read_meaning<'src>(line: &'src str) -> Cow<'src, str> {
     line.trim().into()
 }
 
+/// The kinds of lines in a word list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineKind {
+    /// A comment starting at the beginning of the line, or a blank line.
+    Comment,
+    /// A comment indented like a meaning ('  #').
+    IndentedComment,
+    /// A meaning of the definition above it.
+    Meaning,
+    /// The first line of a definition.
+    Head,
+}
+
+/// Finds what a word list line is.
+fn classify_line(line: &str) -> LineKind {
+    if line.starts_with("#") || line.is_whitespace() {
+        LineKind::Comment
+    } else if line.starts_with("  #") {
+        LineKind::IndentedComment
+    } else if line.starts_with("  ") {
+        LineKind::Meaning
+    } else {
+        LineKind::Head
+    }
+}
+
 /// Reads word definitions from a text and calls 'add_def' for each loaded 
 /// definition.
 pub fn read_definitions_iter<'src, F: FnMut(Def<'src>)>(text: &'src str, mut add_def: F) {
     let mut def: Option<Def<'src>> = None;
     for (i, line) in text.lines().enumerate() {
-        if line.starts_with("#") 
-        || line.starts_with("  #") 
-        || line.is_whitespace() {
-            continue;
-        
-        } else if ! line.starts_with("  ") {
-            if let Some(def) = def.take() {
-                add_def(def);
+        match classify_line(line) {
+            LineKind::Comment | LineKind::IndentedComment => {}
+            LineKind::Head => {
+                if let Some(def) = def.take() {
+                    add_def(def);
+                }
+                def = read_definition(line, i+1);
             }
-            def = read_definition(line, i+1);
-        
-        } else {
-            if let Some(ref mut def) = def {
-                def.meanings.push(read_meaning(line));
-            } else {
-                warn!("Line {}: Meaning found without definition", i+1);
+            LineKind::Meaning => {
+                if let Some(ref mut def) = def {
+                    def.meanings.push(read_meaning(line));
+                } else {
+                    warn!("Line {}: Meaning found without definition", i+1);
+                }
             }
         }
     }
@@ -90,3 +215,222 @@ pub fn read_definitions<'src>(text: &'src str) -> Vec<Def<'src>> {
     read_definitions_iter(text, |def| defs.push(def));
     defs
 }
+
+/// A comment (or blank line) inside a definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefComment<'src> {
+    /// The number of meanings before the comment.
+    pub after_meanings: usize,
+    /// The line as it was written.
+    pub line: Cow<'src, str>,
+}
+
+/// A part of a word list.
+#[derive(Debug, Clone)]
+pub enum WordListItem<'src> {
+    /// A line outside of definitions, like a comment, a blank line or a line
+    /// that could not be read, as it was written.
+    Line(Cow<'src, str>),
+    /// A definition, with the comments between its lines.
+    Def(Def<'src>, Vec<DefComment<'src>>),
+}
+
+/// A word list that keeps its comments, so that it can be edited and
+/// written back.
+/// Reading the written list gives the same definitions.
+#[derive(Debug, Clone, Default)]
+pub struct WordList<'src> {
+    pub items: Vec<WordListItem<'src>>,
+}
+
+impl<'src> WordList<'src> {
+    /// Reads a word list, like 'read_definitions_iter'.
+    /// Indented comments ('  #') belong to the definition above them. Other
+    /// comments and blank lines are kept outside of definitions, unless one
+    /// of the meanings of the definition follows them.
+    pub fn parse(text: &'src str) -> WordList<'src> {
+        let mut items = Vec::new();
+        // The lines after a definition that only belong to it if a meaning
+        // follows
+        let mut pending: Vec<&'src str> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let kind = classify_line(line);
+            if kind == LineKind::Head {
+                for line in pending.drain(..) {
+                    items.push(WordListItem::Line(line.into()));
+                }
+                items.push(match read_definition(line, i+1) {
+                    Some(def) => WordListItem::Def(def, Vec::new()),
+                    None => WordListItem::Line(line.into()),
+                });
+                continue;
+            }
+            if let Some(&mut WordListItem::Def(ref mut def, ref mut comments)) = items.last_mut() {
+                let after_meanings = def.meanings.len();
+                match kind {
+                    LineKind::IndentedComment if pending.is_empty() => {
+                        comments.push(DefComment { after_meanings, line: line.into() });
+                    }
+                    LineKind::Meaning => {
+                        for line in pending.drain(..) {
+                            comments.push(DefComment { after_meanings, line: line.into() });
+                        }
+                        def.meanings.push(read_meaning(line));
+                    }
+                    _ => pending.push(line),
+                }
+                continue;
+            }
+            if kind == LineKind::Meaning {
+                warn!("Line {}: Meaning found without definition", i+1);
+            }
+            items.push(WordListItem::Line(line.into()));
+        }
+        for line in pending {
+            items.push(WordListItem::Line(line.into()));
+        }
+        WordList { items }
+    }
+
+    /// Returns the definitions of the list.
+    pub fn definitions<'a>(&'a self) -> Box<Iterator<Item=&'a Def<'src>> + 'a> {
+        Box::new(self.items.iter().filter_map(|item| match *item {
+            WordListItem::Def(ref def, _) => Some(def),
+            WordListItem::Line(_) => None,
+        }))
+    }
+
+    /// Adds a definition at the end of the list.
+    pub fn push(&mut self, def: Def<'src>) {
+        self.items.push(WordListItem::Def(def, Vec::new()));
+    }
+}
+
+impl<'src> fmt::Display for WordList<'src> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for item in &self.items {
+            match *item {
+                WordListItem::Line(ref line) => writeln!(f, "{}", line)?,
+                WordListItem::Def(ref def, ref comments) => {
+                    write!(f, "{}", def.head())?;
+                    let mut comments = comments.iter().peekable();
+                    for i in 0..def.meanings.len() + 1 {
+                        while let Some(comment) = comments.peek().cloned() {
+                            if comment.after_meanings > i {
+                                break;
+                            }
+                            write!(f, "\n{}", comment.line)?;
+                            comments.next();
+                        }
+                        if let Some(meaning) = def.meanings.get(i) {
+                            write_meaning(meaning, f)?;
+                        }
+                    }
+                    writeln!(f, "")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = "\
+  orphan meaning
+# A word list
+  # An indented comment outside of definitions
+
+가다 | 간 (-去-)
+  # How it's used
+  to go
+# A comment between meanings
+
+  to leave
+  # After the meanings
+# After the definition
+
+유치하다 | 유치한 (幼稚~ | 幼穉-)
+   # not a comment
+  childish
+  # The last line
+학교
+";
+
+    /// Describes each item of the list, with the comments of definitions.
+    fn layout(list: &WordList) -> Vec<String> {
+        list.items.iter().map(|item| match *item {
+            WordListItem::Line(ref line) => format!("line {:?}", line),
+            WordListItem::Def(ref def, ref comments) => {
+                format!("def {:?} {:?} {:?}", def.head(), def.meanings, comments)
+            }
+        }).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let list = WordList::parse(LIST);
+        let written = list.to_string();
+        let reread = WordList::parse(&written);
+        let defs = list.definitions().collect::<Vec<_>>();
+        let reread_defs = reread.definitions().collect::<Vec<_>>();
+        assert_eq!(defs.len(), 3);
+        assert_eq!(defs.len(), reread_defs.len());
+        for (def, reread_def) in defs.iter().zip(&reread_defs) {
+            assert!(def.same_as(reread_def), "{:?} != {:?}", def, reread_def);
+        }
+        assert_eq!(layout(&list), layout(&reread));
+        assert_eq!(reread.to_string(), written);
+    }
+
+    #[test]
+    fn parse_matches_read_definitions() {
+        let list = WordList::parse(LIST);
+        let defs = read_definitions(LIST);
+        assert_eq!(list.definitions().count(), defs.len());
+        for (def, read_def) in list.definitions().zip(&defs) {
+            assert!(def.same_as(read_def), "{:?} != {:?}", def, read_def);
+        }
+    }
+
+    #[test]
+    fn definitions_are_read() {
+        let list = WordList::parse(LIST);
+        let defs = list.definitions().collect::<Vec<_>>();
+        assert_eq!(defs[0].hangeul, "가다");
+        assert_eq!(defs[0].aliases, vec!["간"]);
+        assert_eq!(defs[0].hanja, vec![Hanja { 
+            text: "去".into(), attached_before: true, attached_after: true }]);
+        assert_eq!(defs[0].meanings, vec!["to go", "to leave"]);
+        assert_eq!(defs[1].head(), "유치하다 | 유치한 (幼稚- | 幼穉-)");
+        assert_eq!(defs[1].meanings, vec!["# not a comment", "childish"]);
+        assert!(defs[2].meanings.is_empty());
+    }
+
+    #[test]
+    fn comment_placement() {
+        let list = WordList::parse(LIST);
+        let comment = |after_meanings, line: &'static str| {
+            DefComment { after_meanings, line: line.into() }
+        };
+        assert_eq!(layout(&list), vec![
+            format!("line {:?}", "  orphan meaning"),
+            format!("line {:?}", "# A word list"),
+            format!("line {:?}", "  # An indented comment outside of definitions"),
+            format!("line {:?}", ""),
+            format!("def {:?} {:?} {:?}", "가다 | 간 (-去-)", vec!["to go", "to leave"], vec![
+                comment(0, "  # How it's used"),
+                comment(1, "# A comment between meanings"),
+                comment(1, ""),
+                comment(2, "  # After the meanings"),
+            ]),
+            format!("line {:?}", "# After the definition"),
+            format!("line {:?}", ""),
+            format!("def {:?} {:?} {:?}", "유치하다 | 유치한 (幼稚- | 幼穉-)", 
+                vec!["# not a comment", "childish"], vec![comment(2, "  # The last line")]),
+            format!("def {:?} {:?} {:?}", "학교", Vec::<&str>::new(), Vec::<DefComment>::new()),
+        ]);
+    }
+}
@@ -0,0 +1,334 @@
+//! Module for reading and writing line mode documents ('work files'), where
+//! each source line is followed by its automatic and manual translations.
+
+use std::io::{self, BufRead, Read, Write};
+use std_unicode::str::UnicodeStr;
+use config::Markers;
+
+/// The start of the comment placed above manual lines that were filled in
+/// with an inexact translation memory match. Such lines don't count as
+/// translated until the comment is removed.
+pub const UNREVIEWED_MATCH: &str = "Unreviewed memory match";
+
+/// The kinds of lines that belong to a source line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    /// An automatic translation.
+    Auto,
+    /// A manual translation, which may be empty.
+    Manual,
+    /// A comment by the translator.
+    Comment,
+}
+
+/// A marked line of a work file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub kind: LineKind,
+    /// The line as it was written, including the marker.
+    pub raw: String,
+    /// Where the text after the marker starts.
+    content_start: usize,
+}
+
+impl Line {
+    /// Creates a line with the given marker and text.
+    pub fn new(kind: LineKind, text: &str, markers: &Markers) -> Line {
+        let prefix = match kind {
+            LineKind::Auto => &markers.auto_prefix,
+            LineKind::Manual => &markers.manual_prefix,
+            LineKind::Comment => &markers.comment_prefix,
+        };
+        Line { kind, raw: format!("{} {}", prefix, text), content_start: prefix.len() }
+    }
+
+    /// Reads a line, if it starts with one of the markers.
+    fn parse(raw: &str, markers: &Markers) -> Option<Line> {
+        let (kind, prefix) = if raw.starts_with(&markers.manual_prefix) {
+            (LineKind::Manual, &markers.manual_prefix)
+        } else if raw.starts_with(&markers.auto_prefix) {
+            (LineKind::Auto, &markers.auto_prefix)
+        } else if raw.starts_with(&markers.comment_prefix) {
+            (LineKind::Comment, &markers.comment_prefix)
+        } else {
+            return None;
+        };
+        Some(Line { kind, raw: raw.to_string(), content_start: prefix.len() })
+    }
+
+    /// Returns the text after the marker.
+    pub fn content(&self) -> &str {
+        self.raw[self.content_start..].trim()
+    }
+
+    /// Returns whether nothing has been written after the marker.
+    pub fn is_empty(&self) -> bool {
+        self.content().is_empty()
+    }
+}
+
+/// A source line together with the lines that follow it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub source: String,
+    pub lines: Vec<Line>,
+}
+
+impl Segment {
+    /// Creates a segment for a source line without any translations.
+    pub fn new(source: &str) -> Segment {
+        Segment { source: source.to_string(), lines: Vec::new() }
+    }
+
+    /// Returns the lines of the given kind.
+    pub fn lines_of_kind<'a>(&'a self, kind: LineKind) -> Box<Iterator<Item=&'a Line> + 'a> {
+        Box::new(self.lines.iter().filter(move |line| line.kind == kind))
+    }
+
+    /// Returns the manual translation lines.
+    pub fn manual_lines<'a>(&'a self) -> Box<Iterator<Item=&'a Line> + 'a> {
+        self.lines_of_kind(LineKind::Manual)
+    }
+
+    /// Returns the automatic translation line, if there is one.
+    pub fn auto_line(&self) -> Option<&Line> {
+        self.lines_of_kind(LineKind::Auto).next()
+    }
+
+    /// Returns whether any of the manual lines have been filled in, and they
+    /// aren't an unreviewed memory match.
+    pub fn is_translated(&self) -> bool {
+        self.manual_lines().any(|line| ! line.is_empty()) && ! self.is_unreviewed()
+    }
+
+    /// Returns whether the manual lines were filled in with a memory match
+    /// that hasn't been reviewed yet.
+    pub fn is_unreviewed(&self) -> bool {
+        self.lines_of_kind(LineKind::Comment)
+            .any(|line| line.content().starts_with(UNREVIEWED_MATCH))
+    }
+
+    /// Removes the comment that marks the manual lines as unreviewed.
+    pub fn mark_reviewed(&mut self) {
+        self.lines.retain(|line| {
+            line.kind != LineKind::Comment || ! line.content().starts_with(UNREVIEWED_MATCH)
+        });
+    }
+
+    /// Replaces the automatic translation lines with the given translation,
+    /// which is placed right after the source line.
+    /// No line is added if the translation is empty.
+    pub fn set_auto_translation(&mut self, translation: Option<&str>, markers: &Markers) {
+        self.lines.retain(|line| line.kind != LineKind::Auto);
+        if let Some(translation) = translation {
+            self.lines.insert(0, Line::new(LineKind::Auto, translation, markers));
+        }
+    }
+
+    /// Replaces the manual lines with the given translation, placed where the
+    /// first manual line was.
+    pub fn set_manual_translation(&mut self, translation: &[&str], markers: &Markers) {
+        let pos = self.lines.iter().position(|line| line.kind == LineKind::Manual)
+            .unwrap_or(self.lines.len());
+        self.lines.retain(|line| line.kind != LineKind::Manual);
+        for (i, text) in translation.iter().enumerate() {
+            self.lines.insert(pos + i, Line::new(LineKind::Manual, text, markers));
+        }
+    }
+
+    /// Adds the configured number of empty manual lines.
+    pub fn add_manual_placeholders(&mut self, markers: &Markers) {
+        for _ in 0..markers.manual_lines {
+            self.lines.push(Line::new(LineKind::Manual, "", markers));
+        }
+    }
+
+    /// Writes the segment in the work file format.
+    pub fn write(&self, out: &mut Write) -> io::Result<()> {
+        writeln!(out, "{}", self.source)?;
+        for line in &self.lines {
+            writeln!(out, "{}", line.raw)?;
+        }
+        Ok(())
+    }
+}
+
+/// A part of a work file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    /// A blank line, as it was written.
+    Blank(String),
+    /// A source line and its translations.
+    Segment(Segment),
+    /// A marked line that doesn't follow a source line.
+    Line(Line),
+}
+
+/// A parsed work file, which is written back exactly as it was read, unless
+/// it is modified.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkFile {
+    pub items: Vec<Item>,
+    /// Whether the last line ended with a newline.
+    pub trailing_newline: bool,
+    /// Whether the lines end with '\r\n' instead of '\n'.
+    /// This is detected for the whole file, so a file with mixed line
+    /// endings is written back with '\r\n' on every line.
+    pub crlf: bool,
+}
+
+impl WorkFile {
+    /// Reads a work file that uses the given markers.
+    /// Comment lines followed by an automatic translation are reported, since
+    /// they are most likely source lines that start with the comment prefix.
+    pub fn parse(text: &str, markers: &Markers) -> WorkFile {
+        let mut items = Vec::new();
+        let mut raw_lines = text.split('\n').collect::<Vec<_>>();
+        let trailing_newline = raw_lines.last() == Some(&"");
+        if trailing_newline {
+            raw_lines.pop();
+        }
+        let crlf = text.contains("\r\n");
+        let mut last_kind = None;
+        for (i, raw) in raw_lines.into_iter().enumerate() {
+            let raw = if raw.ends_with('\r') { &raw[..raw.len() - 1] } else { raw };
+            let line = Line::parse(raw, markers);
+            let kind = line.as_ref().map(|line| line.kind);
+            if last_kind == Some(LineKind::Comment) && kind == Some(LineKind::Auto) {
+                warn!("Line {}: The comment above the automatic translation is probably a \
+                    source line starting with the comment prefix {:?}; use another prefix \
+                    to keep it as a source line", i, markers.comment_prefix);
+            }
+            last_kind = kind;
+            if raw.is_whitespace() {
+                items.push(Item::Blank(raw.to_string()));
+            } else if let Some(line) = line {
+                if let Some(&mut Item::Segment(ref mut segment)) = items.last_mut() {
+                    segment.lines.push(line);
+                    continue;
+                }
+                items.push(Item::Line(line));
+            } else {
+                items.push(Item::Segment(Segment::new(raw)));
+            }
+        }
+        WorkFile { items, trailing_newline, crlf }
+    }
+
+    /// Reads a whole work file from the input.
+    pub fn read(input: &mut BufRead, markers: &Markers) -> io::Result<WorkFile> {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        Ok(WorkFile::parse(&text, markers))
+    }
+
+    /// Returns the segments of the file.
+    pub fn segments<'a>(&'a self) -> Box<Iterator<Item=&'a Segment> + 'a> {
+        Box::new(self.items.iter().filter_map(|item| match *item {
+            Item::Segment(ref segment) => Some(segment),
+            _ => None,
+        }))
+    }
+
+    /// Returns the segments of the file with the line numbers of their
+    /// source lines.
+    pub fn numbered_segments<'a>(&'a self) -> Box<Iterator<Item=(usize, &'a Segment)> + 'a> {
+        let mut number = 1;
+        Box::new(self.items.iter().filter_map(move |item| {
+            let start = number;
+            match *item {
+                Item::Segment(ref segment) => {
+                    number += 1 + segment.lines.len();
+                    Some((start, segment))
+                }
+                _ => {
+                    number += 1;
+                    None
+                }
+            }
+        }))
+    }
+
+    /// Returns the segments of the file for modification.
+    pub fn segments_mut<'a>(&'a mut self) -> Box<Iterator<Item=&'a mut Segment> + 'a> {
+        Box::new(self.items.iter_mut().filter_map(|item| match *item {
+            Item::Segment(ref mut segment) => Some(segment),
+            _ => None,
+        }))
+    }
+
+    /// Writes the file in the work file format.
+    pub fn write(&self, out: &mut Write) -> io::Result<()> {
+        let mut text = Vec::new();
+        for item in &self.items {
+            match *item {
+                Item::Blank(ref raw) => writeln!(text, "{}", raw)?,
+                Item::Segment(ref segment) => segment.write(&mut text)?,
+                Item::Line(ref line) => writeln!(text, "{}", line.raw)?,
+            }
+        }
+        if ! self.trailing_newline && text.last() == Some(&b'\n') {
+            text.pop();
+        }
+        if self.crlf {
+            let mut crlf_text = Vec::with_capacity(text.len() + text.len() / 20);
+            for &byte in &text {
+                if byte == b'\n' {
+                    crlf_text.push(b'\r');
+                }
+                crlf_text.push(byte);
+            }
+            text = crlf_text;
+        }
+        out.write_all(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lf_files_are_written_back() {
+        let markers = Markers::default();
+        let text = "-> orphan\n-# note\n\n가다\n-> [to go]\n-# checked\n-| to go\n오다\n-| to come";
+        let work_file = WorkFile::parse(text, &markers);
+        assert!(! work_file.crlf);
+        assert!(! work_file.trailing_newline);
+        assert_eq!(work_file.items.len(), 5);
+        match work_file.items[0] {
+            Item::Line(ref line) => {
+                assert_eq!(line.kind, LineKind::Auto);
+                assert_eq!(line.content(), "orphan");
+            }
+            ref other => panic!("{:?}", other),
+        }
+        match work_file.items[1] {
+            Item::Line(ref line) => assert_eq!(line.kind, LineKind::Comment),
+            ref other => panic!("{:?}", other),
+        }
+        assert_eq!(work_file.items[2], Item::Blank(String::new()));
+        let segments = work_file.segments().collect::<Vec<_>>();
+        assert_eq!(segments.len(), 2);
+        let kinds = segments[0].lines.iter().map(|line| line.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![LineKind::Auto, LineKind::Comment, LineKind::Manual]);
+        assert!(segments[1].is_translated());
+        let mut written = Vec::new();
+        work_file.write(&mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), text);
+    }
+
+    #[test]
+    fn crlf_lines_are_written_back() {
+        let markers = Markers::default();
+        let text = "가다\r\n-> [to go]\r\n-| \r\n\r\n";
+        let work_file = WorkFile::parse(text, &markers);
+        assert!(work_file.crlf);
+        let segment = work_file.segments().next().unwrap();
+        assert_eq!(segment.source, "가다");
+        assert_eq!(segment.auto_line().unwrap().content(), "[to go]");
+        let mut written = Vec::new();
+        work_file.write(&mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), text);
+    }
+}